@@ -1,57 +1,55 @@
-use crate::block::{Block, Orientation};
-use crate::consts::*;
-use crate::game_state::GameState;
+use crate::block::Rotation;
+use crate::config::HoldMode;
+use crate::engine::{Engine, GameEvent};
 
-impl GameState {
+impl Engine {
     pub fn hard_drop(&mut self) {
-        self.try_translate(0, self.current_block.max_drop(&self.squares));
-        self.update_timer = TICK_INTERVAL;
+        let rows = self
+            .current_block
+            .max_drop(&self.squares, self.config.board);
+        if rows > 0 && self.try_translate(0, rows) {
+            self.score.hard_drop(rows as usize);
+        }
+        self.lock();
     }
 
+    /// puts the current block in the hold, taking out the oldest
+    /// held block or the next one in the queue in its place
     pub fn cache(&mut self) {
-        if !self.used_hold {
-            self.used_hold = true;
-            let saved_current = self.current_block.blocktype;
-            self.current_block = match self.held_block {
-                Some(blocktype) => {
-                    Block::new(blocktype, Orientation::Up).translate(X_SQUARES / 2, -5)
-                }
-                None => {
-                    // reset the queue
-                    if self.block_index == 14 {
-                        self.update_queue();
-                        self.block_index = 0;
-                    }
+        let slots = self.config.hold.slots();
+        if slots == 0 || self.used_hold {
+            return;
+        }
+        self.used_hold = self.config.hold != HoldMode::Unlimited;
 
-                    let new_blocktype = TYPES[self.queue[self.block_index]];
-                    self.block_index += 1;
+        let saved_current = self.current_block.blocktype;
+        self.held_blocks.push_back(saved_current);
+        let new_blocktype = if self.held_blocks.len() > slots {
+            self.held_blocks.pop_front().unwrap()
+        } else {
+            self.next_blocktype()
+        };
 
-                    Block::new(new_blocktype, Orientation::Up).translate(X_SQUARES / 2, -5)
-                }
-            };
-            self.held_block = Some(saved_current);
-        }
+        self.spawn(new_blocktype);
+        self.events.push(GameEvent::Held(saved_current));
     }
 
-    pub fn spin(&mut self) {
-        let mut rotated = self.current_block.rotate();
+    /// rotates the current block, trying each kick in order
+    /// until one doesn't collide with the walls or the stack
+    pub fn spin(&mut self, rotation: Rotation) {
+        let rotated = self.current_block.rotate(rotation);
 
-        let overflow = rotated
-            .squares
+        if let Some((kick, kicked)) = self
+            .current_block
+            .kicks(rotation)
             .iter()
-            .fold(0, |over, square| {
-                if square.pos.0 >= X_SQUARES && square.pos.0 - X_SQUARES + 1 > over {
-                    square.pos.0 - X_SQUARES + 1
-                } else if square.pos.0 < 0 && square.pos.0 < over {
-                    square.pos.0
-                } else {
-                    over
-                }
-            });
-        
-        rotated = rotated.translate(-overflow, 0);
-        if rotated.is_valid(&self.squares) {
-            self.current_block = rotated.translate(-overflow, 0);
+            .map(|&(x, y)| rotated.translate(x, y))
+            .enumerate()
+            .find(|(_, kicked)| kicked.is_valid(&self.squares, self.config.board))
+        {
+            self.set_current_block(kicked);
+            self.last_rotation = Some((rotation, kick));
+            self.das_cut_timer = self.config.handling.das_cut_frames();
         }
     }
 }
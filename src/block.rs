@@ -1,6 +1,6 @@
-use ggez::graphics::{Color, Rect};
+use ggez::graphics::Color;
 
-use crate::consts::*;
+use crate::config::BoardSize;
 use std::convert::TryInto;
 
 // Color::from_rgb can't be static
@@ -16,66 +16,30 @@ static T_COLOR: Color = Color::new(120. / 255., 114. / 255., 204. / 255., 1.0);
 /// A square that is or was part of a block
 #[derive(Clone, Copy, Debug)]
 pub struct Square {
-    /// Graphical component of the square
-    pub rect: Rect,
     /// Logical position of the square
     pub pos: (i8, i8),
     pub color: Color,
 }
 
 impl Square {
-    /// creates an identical square at the bottom of the screen
-    fn bottom(x: i8) -> Self {
-        Square {
-            rect: Rect::new(f32::from(x) * SQUARE_SIZE, SCREEN_HEIGHT, 5., 5.),
-            pos: (x, Y_SQUARES),
-            color: Color::new(1.0, 1.0, 1.0, 1.0),
-        }
-    }
-
-    /// finds the maximum distance a square could fall
-    pub fn max_y_translate(&self, board: &[Square]) -> i8 {
-        // starts by filtering the board to only squares on the same x axis, and then
-        // looks down
-        let max_square = board
-            .iter()
-            .filter(|square| square.pos.0 == self.pos.0 && square.pos.1 >= self.pos.1)
-            .fold(Square::bottom(self.pos.0), |max_square, current_square| {
-                if current_square.pos.1 <= max_square.pos.1 {
-                    *current_square
-                } else {
-                    max_square
-                }
-            });
-        max_square.pos.1 - self.pos.1
-    }
-
     /// returns a translated version of the square
     pub fn translate(&self, x: i8, y: i8) -> Square {
-        let mut new_rect = self.rect;
-        new_rect.translate([f32::from(x) * SQUARE_SIZE, f32::from(y) * SQUARE_SIZE]);
         Square {
-            rect: new_rect,
             pos: (self.pos.0 + x, self.pos.1 + y),
             color: self.color,
         }
     }
 
-    fn new(x: i8, y: i8, color: Color) -> Self {
-        Square {
-            rect: Rect::new(
-                f32::from(x) * SQUARE_SIZE + BORDER_SIZE,
-                f32::from(y) * SQUARE_SIZE + BORDER_SIZE,
-                SQUARE_SIZE - (BORDER_SIZE * 2.),
-                SQUARE_SIZE - (BORDER_SIZE * 2.),
-            ),
-            pos: (x, y),
-            color,
-        }
+    pub fn new(x: i8, y: i8, color: Color) -> Self {
+        Square { pos: (x, y), color }
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+/// The rotation state of a block, following SRS naming:
+/// `Up` is the spawn state (0), `Right` is one clockwise
+/// rotation from spawn (R), `Down` is two (2), and `Left`
+/// is one counter-clockwise rotation from spawn (L)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Orientation {
     Left,
     Right,
@@ -83,7 +47,52 @@ pub enum Orientation {
     Down,
 }
 
-#[derive(Copy, Clone, Debug)]
+impl Orientation {
+    /// the orientation after a clockwise rotation
+    pub fn cw(self) -> Self {
+        match self {
+            Orientation::Up => Orientation::Right,
+            Orientation::Right => Orientation::Down,
+            Orientation::Down => Orientation::Left,
+            Orientation::Left => Orientation::Up,
+        }
+    }
+
+    /// the orientation after a counter-clockwise rotation
+    pub fn ccw(self) -> Self {
+        self.cw().cw().cw()
+    }
+
+    /// the orientation after a given rotation
+    pub fn rotated(self, rotation: Rotation) -> Self {
+        match rotation {
+            Rotation::Clockwise => self.cw(),
+            Rotation::CounterClockwise => self.ccw(),
+            Rotation::Half => self.cw().cw(),
+        }
+    }
+
+    /// index into the SRS tables, in the order 0, R, 2, L
+    fn index(self) -> usize {
+        match self {
+            Orientation::Up => 0,
+            Orientation::Right => 1,
+            Orientation::Down => 2,
+            Orientation::Left => 3,
+        }
+    }
+}
+
+/// A direction a block can be rotated in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rotation {
+    Clockwise,
+    CounterClockwise,
+    /// a 180° rotation
+    Half,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum BlockType {
     Line,
     Square,
@@ -103,6 +112,9 @@ pub struct Block {
     pub squares: [Square; 4],
     pub blocktype: BlockType,
     pub orientation: Orientation,
+    /// Logical position of the top left corner of the block's
+    /// bounding box, which blocks rotate within
+    pub pos: (i8, i8),
 }
 
 pub fn color(blocktype: BlockType) -> Color {
@@ -132,30 +144,84 @@ fn block_from_squares(
             .unwrap(),
         blocktype,
         orientation,
+        pos: (0, 0),
     }
 }
 
+// SRS wall kicks for clockwise rotations, indexed by the orientation
+// being rotated from (0, R, 2, L). Offsets are written (x, y) with
+// y pointing up, the same as the usual SRS tables, and are tried in
+// order until one of them fits. Counter-clockwise kicks are the
+// clockwise kicks of the opposite transition, negated.
+#[rustfmt::skip]
+static JLSTZ_KICKS: [[(i8, i8); 5]; 4] = [
+    [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)], // 0 -> R
+    [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],     // R -> 2
+    [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],    // 2 -> L
+    [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],  // L -> 0
+];
+
+#[rustfmt::skip]
+static LINE_KICKS: [[(i8, i8); 5]; 4] = [
+    [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)],   // 0 -> R
+    [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)],   // R -> 2
+    [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)],   // 2 -> L
+    [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)],   // L -> 0
+];
+
+// SRS doesn't define 180° rotations, so these are the kicks
+// popularized by TETR.IO, used for every block but the square
+#[rustfmt::skip]
+static HALF_KICKS: [[(i8, i8); 6]; 4] = [
+    [(0, 0), (0, 1), (1, 1), (-1, 1), (1, 0), (-1, 0)],     // 0 -> 2
+    [(0, 0), (1, 0), (1, 2), (1, 1), (0, 2), (0, 1)],       // R -> L
+    [(0, 0), (0, -1), (-1, -1), (1, -1), (-1, 0), (1, 0)],  // 2 -> 0
+    [(0, 0), (-1, 0), (-1, 2), (-1, 1), (0, 2), (0, 1)],    // L -> R
+];
+
+static NO_KICKS: [(i8, i8); 1] = [(0, 0)];
+
 #[rustfmt::skip]
 impl Block {
     pub fn new(blocktype: BlockType, orientation: Orientation) -> Self {
         // positions are done by (x, y) rather than (row, col)
+        // within each block's bounding box, using the SRS rotation states
         match (blocktype, orientation) {
             (BlockType::Line, Orientation::Up) => {
                 block_from_squares(blocktype, orientation, 
                     [
-                        (0, 0), 
-                        (0, 1),
-                        (0, 2),
-                        (0, 3)
+                     (0, 1), (1, 1), (2, 1), (3, 1)
+                    ]
+                )
+            }
+            (BlockType::Line, Orientation::Right) => {
+                block_from_squares(blocktype, orientation, 
+                    [
+                             (2, 0),
+                             (2, 1),
+                             (2, 2),
+                             (2, 3)
+                    ]
+                )
+            }
+            (BlockType::Line, Orientation::Down) => {
+                block_from_squares(blocktype, orientation, 
+                    [
+                     (0, 2), (1, 2), (2, 2), (3, 2)
                     ]
                 )
             }
             (BlockType::Line, Orientation::Left) => {
                 block_from_squares(blocktype, orientation, 
-                    [(0, 0), (1, 0), (2, 0), (3, 0)]
+                    [
+                             (1, 0),
+                             (1, 1),
+                             (1, 2),
+                             (1, 3)
+                    ]
                 )
             }
-            (BlockType::Square, Orientation::Up) => {
+            (BlockType::Square, _) => {
                 block_from_squares(blocktype, orientation, 
                     [(0, 0), (1, 0), 
                      (0, 1), (1, 1)]
@@ -163,68 +229,97 @@ impl Block {
             }
             (BlockType::L, Orientation::Up) => {
                 block_from_squares(blocktype, orientation, 
-                    [(0, 0), 
-                     (0, 1), 
-                     (0, 2), (1, 2)]
+                    [                (2, 0),
+                     (0, 1), (1, 1), (2, 1)
+                    ]
                 )
             }
             (BlockType::L, Orientation::Right) => {
                 block_from_squares(blocktype, orientation, 
-                    [(0, 0), (1, 0), (2, 0),
-                     (0, 1)
+                    [
+                             (1, 0),
+                             (1, 1),
+                             (1, 2), (2, 2)
                     ]
                 )
             }
             (BlockType::L, Orientation::Down) => {
                 block_from_squares(blocktype, orientation, 
-                    [(0, 0), (1, 0), 
-                     (1, 1),
-                     (1, 2)
+                    [
+                     (0, 1), (1, 1), (2, 1),
+                     (0, 2)
                     ]
                 )
             }
             (BlockType::L, Orientation::Left) => {
                 block_from_squares(blocktype, orientation, 
-                    [                (2, 0),
-                     (0, 1), (1, 1), (2, 1)
+                    [
+                     (0, 0), (1, 0),
+                             (1, 1),
+                             (1, 2)
                     ]
                 )
             }
             (BlockType::ReverseL, Orientation::Up) => {
                 block_from_squares(blocktype, orientation, 
                     [
-                             (1, 0),
-                             (1, 1),
-                     (0, 2), (1, 2)
+                     (0, 0),
+                     (0, 1), (1, 1), (2, 1)
                     ]
                 )
             }
             (BlockType::ReverseL, Orientation::Right) => {
                 block_from_squares(blocktype, orientation, 
                     [
-                     (0, 0),
-                     (0, 1), (1, 1), (2, 1)
+                             (1, 0), (2, 0),
+                             (1, 1),
+                             (1, 2)
                     ]
                 )
             }
             (BlockType::ReverseL, Orientation::Down) => {
                 block_from_squares(blocktype, orientation, 
                     [
-                     (0, 0), (1, 0), 
-                     (0, 1), 
-                     (0, 2)
+                     (0, 1), (1, 1), (2, 1),
+                                     (2, 2)
                     ]
                 )
             }
             (BlockType::ReverseL, Orientation::Left) => {
                 block_from_squares(blocktype, orientation, 
                     [
-                     (0, 0), (1, 0), (2, 0), 
-                                     (2, 1)
+                             (1, 0),
+                             (1, 1),
+                     (0, 2), (1, 2)
                     ]
                 )
             }
             (BlockType::S, Orientation::Up) => {
+                block_from_squares(blocktype, orientation, 
+                    [
+                             (1, 0), (2, 0),
+                     (0, 1), (1, 1)
+                    ]
+                )
+            }
+            (BlockType::S, Orientation::Right) => {
+                block_from_squares(blocktype, orientation, 
+                    [
+                             (1, 0),
+                             (1, 1), (2, 1),
+                                     (2, 2)
+                    ]
+                )
+            }
+            (BlockType::S, Orientation::Down) => {
+                block_from_squares(blocktype, orientation, 
+                    [
+                             (1, 1), (2, 1),
+                     (0, 2), (1, 2)
+                    ]
+                )
+            }
+            (BlockType::S, Orientation::Left) => {
                 block_from_squares(blocktype, orientation, 
                     [
                      (0, 0),
@@ -233,28 +328,37 @@ impl Block {
                     ]
                 )
             }
-            (BlockType::S, Orientation::Left) => {
+            (BlockType::Z, Orientation::Up) => {
                 block_from_squares(blocktype, orientation, 
                     [
-                             (1, 0), (2, 0),
-                     (0, 1), (1, 1)
+                     (0, 0), (1, 0), 
+                             (1, 1), (2, 1)
                     ]
                 )
             }
-            (BlockType::Z, Orientation::Up) => {
+            (BlockType::Z, Orientation::Right) => {
                 block_from_squares(blocktype, orientation, 
                     [
-                             (1, 0),
-                     (0, 1), (1, 1),
-                     (0, 2)
+                                     (2, 0),
+                             (1, 1), (2, 1),
+                             (1, 2)
+                    ]
+                )
+            }
+            (BlockType::Z, Orientation::Down) => {
+                block_from_squares(blocktype, orientation, 
+                    [
+                     (0, 1), (1, 1), 
+                             (1, 2), (2, 2)
                     ]
                 )
             }
             (BlockType::Z, Orientation::Left) => {
                 block_from_squares(blocktype, orientation, 
                     [
-                     (0, 0), (1, 0), 
-                             (1, 1), (2, 1)
+                             (1, 0),
+                     (0, 1), (1, 1),
+                     (0, 2)
                     ]
                 )
             }
@@ -269,17 +373,17 @@ impl Block {
             (BlockType::T, Orientation::Right) => {
                 block_from_squares(blocktype, orientation, 
                     [
-                     (0, 0), 
-                     (0, 1), (1, 1), 
-                     (0, 2)
+                             (1, 0), 
+                             (1, 1), (2, 1),
+                             (1, 2)
                     ]
                 )
             }
             (BlockType::T, Orientation::Down) => {
                 block_from_squares(blocktype, orientation, 
                     [
-                     (0, 0), (1, 0), (2, 0), 
-                             (1, 1)
+                     (0, 1), (1, 1), (2, 1), 
+                             (1, 2)
                     ]
                 )
             }
@@ -292,35 +396,42 @@ impl Block {
                     ]
                 )
             }
-            _ => {
-                unreachable!();
-            }
         }
     }
 
-    pub fn rotate(&self) -> Block {
-        match (self.blocktype, self.orientation){
-            (BlockType::Line, Orientation::Left) => Block::new(BlockType::Line, Orientation::Up).translate(2, 0),
-            (BlockType::Line, Orientation::Up) => Block::new(BlockType::Line, Orientation::Left).translate(-2, 0),
-            (BlockType::Square, Orientation::Up) => Block::new(BlockType::Square, Orientation::Up),
-            (BlockType::L, Orientation::Up) => Block::new(BlockType::L, Orientation::Right),
-            (BlockType::L, Orientation::Right) => Block::new(BlockType::L, Orientation::Down),
-            (BlockType::L, Orientation::Down) => Block::new(BlockType::L, Orientation::Left).translate(-1, 0),
-            (BlockType::L, Orientation::Left) => Block::new(BlockType::L, Orientation::Up).translate(-1, 0),
-            (BlockType::ReverseL, Orientation::Up) => Block::new(BlockType::ReverseL, Orientation::Right),
-            (BlockType::ReverseL, Orientation::Right) => Block::new(BlockType::ReverseL, Orientation::Down),
-            (BlockType::ReverseL, Orientation::Down) => Block::new(BlockType::ReverseL, Orientation::Left).translate(-1, 0),
-            (BlockType::ReverseL, Orientation::Left) => Block::new(BlockType::ReverseL, Orientation::Up),
-            (BlockType::S, Orientation::Up) => Block::new(BlockType::S, Orientation::Left),
-            (BlockType::S, Orientation::Left) => Block::new(BlockType::S, Orientation::Up).translate(-1, 0),
-            (BlockType::Z, Orientation::Up) => Block::new(BlockType::Z, Orientation::Left),
-            (BlockType::Z, Orientation::Left) => Block::new(BlockType::Z, Orientation::Up).translate(-1, 0),
-            (BlockType::T, Orientation::Up) => Block::new(BlockType::T, Orientation::Right),
-            (BlockType::T, Orientation::Right) => Block::new(BlockType::T, Orientation::Down).translate(-1, 0),
-            (BlockType::T, Orientation::Down) => Block::new(BlockType::T, Orientation::Left),
-            (BlockType::T, Orientation::Left) => Block::new(BlockType::T, Orientation::Up).translate(-1, 0),
-            _ => unreachable!(),
-        }.translate(self.squares[0].pos.0, self.squares[1].pos.1)
+    /// a new block at its guideline spawn position, in the two hidden rows
+    /// just above the visible board. The line and square are centered
+    /// and the rest are centered rounding to the left
+    pub fn spawn(blocktype: BlockType, size: BoardSize) -> Self {
+        let block = Block::new(blocktype, Orientation::Up);
+        let min_x = block.squares.iter().map(|square| square.pos.0).min().unwrap();
+        let max_x = block.squares.iter().map(|square| square.pos.0).max().unwrap();
+        let min_y = block.squares.iter().map(|square| square.pos.1).min().unwrap();
+
+        block.translate((size.width - (max_x - min_x + 1)) / 2 - min_x, -2 - min_y)
+    }
+
+    /// returns the block rotated in place, without any kicks
+    pub fn rotate(&self, rotation: Rotation) -> Block {
+        Block::new(self.blocktype, self.orientation.rotated(rotation))
+            .translate(self.pos.0, self.pos.1)
+    }
+
+    /// the kick offsets to try when rotating from the current
+    /// orientation, with y pointing down like the board
+    pub fn kicks(&self, rotation: Rotation) -> Vec<(i8, i8)> {
+        let from = self.orientation.index();
+        let to = self.orientation.rotated(rotation).index();
+        let (table, sign): (&[(i8, i8)], i8) = match (self.blocktype, rotation) {
+            (BlockType::Square, _) => (&NO_KICKS, 1),
+            (_, Rotation::Half) => (&HALF_KICKS[from], 1),
+            (BlockType::Line, Rotation::Clockwise) => (&LINE_KICKS[from], 1),
+            (BlockType::Line, Rotation::CounterClockwise) => (&LINE_KICKS[to], -1),
+            (_, Rotation::Clockwise) => (&JLSTZ_KICKS[from], 1),
+            (_, Rotation::CounterClockwise) => (&JLSTZ_KICKS[to], -1),
+        };
+
+        table.iter().map(|&(x, y)| (x * sign, -y * sign)).collect()
     }
 
     pub fn translate(&self, x: i8, y: i8) -> Block {
@@ -333,6 +444,7 @@ impl Block {
             squares: cloned.as_slice().try_into().unwrap(),
             orientation: self.orientation,
             blocktype: self.blocktype,
+            pos: (self.pos.0 + x, self.pos.1 + y),
         }
     }
 
@@ -344,21 +456,133 @@ impl Block {
         })
     }
 
-    pub fn is_valid(&self, board: &[Square]) -> bool {
+    pub fn is_valid(&self, board: &[Square], size: BoardSize) -> bool {
         !self.overlaps(board)
             && !self.squares.iter().any(|&square| {
-                square.pos.0 < 0 || square.pos.0 >= X_SQUARES || square.pos.1 >= Y_SQUARES
+                square.pos.0 < 0
+                    || square.pos.0 >= size.width
+                    || square.pos.1 >= size.height
+                    || square.pos.1 < -size.buffer_height
             })
     }
 
-    pub fn max_drop(&self, board: &[Square]) -> i8 {
-        self.squares.iter().fold(Y_SQUARES + 5, |max_dist, square| {
-            let square_max = square.max_y_translate(board);
-            if square_max < max_dist {
-                square_max
-            } else {
-                max_dist
+    /// finds the maximum distance the block could fall
+    pub fn max_drop(&self, board: &[Square], size: BoardSize) -> i8 {
+        let mut drop = 0;
+        while self.translate(0, drop + 1).is_valid(board, size) {
+            drop += 1;
+        }
+        drop
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consts::TYPES;
+
+    const ORIENTATIONS: [Orientation; 4] = [
+        Orientation::Up,
+        Orientation::Right,
+        Orientation::Down,
+        Orientation::Left,
+    ];
+
+    fn positions(block: &Block) -> Vec<(i8, i8)> {
+        let mut positions: Vec<(i8, i8)> = block.squares.iter().map(|square| square.pos).collect();
+        positions.sort_unstable();
+        positions
+    }
+
+    #[test]
+    fn four_rotations_come_back_around() {
+        for &blocktype in TYPES.iter() {
+            let block = Block::new(blocktype, Orientation::Up).translate(3, 5);
+            let mut rotated = block.clone();
+            for _ in 0..4 {
+                rotated = rotated.rotate(Rotation::Clockwise);
+            }
+            assert_eq!(positions(&rotated), positions(&block), "{:?}", blocktype);
+            assert_eq!(rotated.pos, block.pos);
+        }
+    }
+
+    #[test]
+    fn kicks_point_down_the_board() {
+        // 0 -> R tries one left, then one left and one up
+        let kicks = Block::new(BlockType::T, Orientation::Up).kicks(Rotation::Clockwise);
+        assert_eq!(kicks, vec![(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)]);
+
+        let kicks = Block::new(BlockType::Line, Orientation::Up).kicks(Rotation::Clockwise);
+        assert_eq!(kicks, vec![(0, 0), (-2, 0), (1, 0), (-2, 1), (1, -2)]);
+    }
+
+    #[test]
+    fn square_never_kicks() {
+        for &orientation in ORIENTATIONS.iter() {
+            let block = Block::new(BlockType::Square, orientation);
+            assert_eq!(block.kicks(Rotation::Clockwise), vec![(0, 0)]);
+        }
+    }
+
+    #[test]
+    fn counter_clockwise_kicks_undo_clockwise_ones() {
+        for &blocktype in [BlockType::T, BlockType::Line].iter() {
+            for &orientation in ORIENTATIONS.iter() {
+                let ccw = Block::new(blocktype, orientation).kicks(Rotation::CounterClockwise);
+                let cw = Block::new(blocktype, orientation.ccw()).kicks(Rotation::Clockwise);
+                let undone: Vec<(i8, i8)> = cw.iter().map(|&(x, y)| (-x, -y)).collect();
+                assert_eq!(ccw, undone, "{:?} {:?}", blocktype, orientation);
             }
-        }) - 1
+        }
+    }
+
+    #[test]
+    fn half_rotations_use_their_own_kicks() {
+        for &blocktype in TYPES.iter() {
+            let block = Block::new(blocktype, Orientation::Up).translate(3, 5);
+            let half = block.rotate(Rotation::Half);
+            assert_eq!(half.orientation, Orientation::Down);
+            let back = half.rotate(Rotation::Half);
+            assert_eq!(positions(&back), positions(&block), "{:?}", blocktype);
+        }
+
+        let kicks = Block::new(BlockType::T, Orientation::Up).kicks(Rotation::Half);
+        assert_eq!(kicks[..3], [(0, 0), (0, -1), (1, -1)]);
+        let square = Block::new(BlockType::Square, Orientation::Up);
+        assert_eq!(square.kicks(Rotation::Half), vec![(0, 0)]);
+    }
+
+    #[test]
+    fn spawns_centered_in_the_buffer_zone() {
+        let size = BoardSize::default();
+
+        let t = Block::spawn(BlockType::T, size);
+        assert_eq!(positions(&t), vec![(3, -1), (4, -2), (4, -1), (5, -1)]);
+
+        let line = Block::spawn(BlockType::Line, size);
+        assert_eq!(positions(&line), vec![(3, -2), (4, -2), (5, -2), (6, -2)]);
+
+        let square = Block::spawn(BlockType::Square, size);
+        assert_eq!(positions(&square), vec![(4, -2), (4, -1), (5, -2), (5, -1)]);
+    }
+
+    #[test]
+    fn stays_inside_the_board_and_buffer() {
+        let size = BoardSize::default();
+        let block = Block::spawn(BlockType::Line, size);
+
+        assert!(block.is_valid(&[], size));
+        assert!(!block.translate(-4, 0).is_valid(&[], size));
+        assert!(!block.translate(4, 0).is_valid(&[], size));
+        assert!(!block.translate(0, 22).is_valid(&[], size));
+        assert!(!block
+            .translate(0, -size.buffer_height + 1)
+            .is_valid(&[], size));
+        assert_eq!(block.max_drop(&[], size), 21);
+
+        let stack = [Square::new(4, 10, color(BlockType::T))];
+        assert!(!block.translate(0, 12).is_valid(&stack, size));
+        assert_eq!(block.max_drop(&stack, size), 11);
     }
 }
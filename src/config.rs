@@ -0,0 +1,390 @@
+use crate::consts::*;
+use crate::garbage::GarbageSettings;
+use crate::gravity::GravityCurve;
+use crate::randomizer::RandomizerKind;
+
+/// Dimensions of the board in squares
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BoardSize {
+    pub width: i8,
+    /// rows that are shown on screen
+    pub height: i8,
+    /// hidden rows above the visible ones that blocks can still move through
+    pub buffer_height: i8,
+}
+
+impl Default for BoardSize {
+    fn default() -> Self {
+        BoardSize {
+            width: X_SQUARES,
+            height: Y_SQUARES,
+            buffer_height: BUFFER_HEIGHT,
+        }
+    }
+}
+
+/// How many blocks can be held, and how often
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HoldMode {
+    Disabled,
+    /// one slot, which can be used once per block
+    Classic,
+    /// one slot, which can be swapped with any number of times
+    Unlimited,
+    /// several slots, which can be used once per block. Holding
+    /// puts the current block in the last slot, and takes the
+    /// first block out once all of them are full
+    MultiSlot(usize),
+}
+
+impl HoldMode {
+    /// how many blocks can be held at once
+    pub fn slots(self) -> usize {
+        match self {
+            HoldMode::Disabled => 0,
+            HoldMode::Classic | HoldMode::Unlimited => 1,
+            HoldMode::MultiSlot(slots) => slots,
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        match text {
+            "none" => Ok(HoldMode::Disabled),
+            "classic" => Ok(HoldMode::Classic),
+            "unlimited" => Ok(HoldMode::Unlimited),
+            _ => match text.parse() {
+                Ok(slots) if (1..=MAX_HOLD_SLOTS).contains(&slots) => {
+                    Ok(HoldMode::MultiSlot(slots))
+                }
+                _ => Err(format!("unknown hold mode: {}", text)),
+            },
+        }
+    }
+}
+
+/// How held inputs repeat, with times in milliseconds
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Handling {
+    /// delayed auto shift, how long a direction has to be held
+    /// before it starts repeating
+    pub das: u32,
+    /// auto repeat rate, the time between repeats once DAS has
+    /// charged. 0 moves the block as far as it can go at once
+    pub arr: u32,
+    /// DAS cut delay, how long a charged direction waits before
+    /// shifting a block that just spawned or rotated
+    pub das_cut: u32,
+    /// how many times faster than gravity soft drop is, or
+    /// None to drop straight to the bottom (sonic drop)
+    pub soft_drop_factor: Option<f32>,
+}
+
+impl Handling {
+    pub fn das_frames(self) -> f32 {
+        self.das as f32 / FRAME_MS
+    }
+
+    pub fn arr_frames(self) -> f32 {
+        self.arr as f32 / FRAME_MS
+    }
+
+    pub fn das_cut_frames(self) -> f32 {
+        self.das_cut as f32 / FRAME_MS
+    }
+}
+
+impl Default for Handling {
+    fn default() -> Self {
+        Handling {
+            das: DAS,
+            arr: ARR,
+            das_cut: DAS_CUT,
+            soft_drop_factor: Some(SOFT_DROP_FACTOR),
+        }
+    }
+}
+
+/// Settings for a single game, fixed once the game starts
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub board: BoardSize,
+    /// frames a block can rest on the stack before it locks
+    pub lock_delay: usize,
+    /// how many times moving or rotating a resting block can
+    /// restart its lock delay before it locks regardless
+    pub max_lock_resets: usize,
+    /// frames between a block locking and the next one spawning,
+    /// also known as ARE
+    pub entry_delay: usize,
+    /// frames full rows stay on the board before they're removed
+    pub line_clear_delay: usize,
+    /// level the game starts at, counting from 1
+    pub start_level: usize,
+    /// lines needed to go up a level
+    pub lines_per_level: usize,
+    /// level the game stops going up at, if any
+    pub max_level: Option<usize>,
+    pub gravity: GravityCurve,
+    pub randomizer: RandomizerKind,
+    /// how many upcoming blocks are shown, up to `MAX_PREVIEWS`
+    pub previews: usize,
+    pub hold: HoldMode,
+    pub handling: Handling,
+    /// rows of garbage the board starts with, none if None. It
+    /// can't be more than the height of the board
+    pub garbage_rows: Option<usize>,
+    pub garbage: GarbageSettings,
+    /// whether locking a block partly above the visible
+    /// board ends the game, rather than only entirely above it
+    pub partial_lock_out: bool,
+    /// Initial Hold System, which holds a new block as it
+    /// spawns if hold is already pressed
+    pub ihs: bool,
+    /// seed for the game's randomness, picked at random if None
+    pub seed: Option<u64>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            board: BoardSize::default(),
+            lock_delay: LOCK_DELAY,
+            max_lock_resets: MAX_LOCK_RESETS,
+            entry_delay: ENTRY_DELAY,
+            line_clear_delay: LINE_CLEAR_DELAY,
+            start_level: 1,
+            lines_per_level: LINES_PER_LEVEL,
+            max_level: None,
+            gravity: GravityCurve::guideline(),
+            randomizer: RandomizerKind::FourteenBag,
+            previews: PREVIEWS,
+            hold: HoldMode::Classic,
+            handling: Handling::default(),
+            garbage_rows: None,
+            garbage: GarbageSettings::default(),
+            partial_lock_out: false,
+            ihs: false,
+            seed: None,
+        }
+    }
+}
+
+impl Config {
+    /// builds a config from command line arguments such as
+    /// `--level 5 --gravity nes`, using defaults for anything missing
+    pub fn from_args(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut config = Config::default();
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("missing value for {}", arg))
+            };
+
+            match arg.as_str() {
+                "--level" => config.start_level = parse_number(&value()?)?.max(1),
+                "--lines-per-level" => config.lines_per_level = parse_number(&value()?)?.max(1),
+                "--gravity" => config.gravity = GravityCurve::parse(&value()?)?,
+                "--width" => config.board.width = parse_size(&value()?, 4)?,
+                "--height" => config.board.height = parse_size(&value()?, 4)?,
+                "--buffer" => {
+                    config.board.buffer_height = parse_size(&value()?, MIN_BUFFER_HEIGHT)?
+                }
+                "--randomizer" => config.randomizer = RandomizerKind::parse(&value()?)?,
+                "--previews" => config.previews = parse_number(&value()?)?.min(MAX_PREVIEWS),
+                "--hold" => config.hold = HoldMode::parse(&value()?)?,
+                "--ihs" => config.ihs = true,
+                "--das" => config.handling.das = parse_ms(&value()?)?,
+                "--arr" => config.handling.arr = parse_ms(&value()?)?,
+                "--das-cut" => config.handling.das_cut = parse_ms(&value()?)?,
+                "--sdf" => config.handling.soft_drop_factor = parse_soft_drop_factor(&value()?)?,
+                "--garbage" => config.garbage_rows = Some(parse_number(&value()?)?),
+                "--garbage-hole" => {
+                    config.garbage.hole = Some(parse_size(&value()?, 1)? - 1);
+                }
+                "--messiness" => config.garbage.messiness = Some(parse_chance(&value()?)?),
+                "--partial-lock-out" => config.partial_lock_out = true,
+                "--seed" => {
+                    let seed = value()?;
+                    config.seed = Some(
+                        seed.parse()
+                            .map_err(|_| format!("expected a seed, got {}", seed))?,
+                    );
+                }
+                "--lock-delay" => config.lock_delay = parse_number(&value()?)?,
+                "--entry-delay" => config.entry_delay = parse_number(&value()?)?,
+                "--line-clear-delay" => config.line_clear_delay = parse_number(&value()?)?,
+                _ => return Err(format!("unknown argument: {}", arg)),
+            }
+        }
+
+        // checked once all the arguments are in, since the
+        // board size can come after the garbage
+        if let Some(rows) = config.garbage_rows {
+            if rows > config.board.height as usize {
+                return Err(format!(
+                    "expected at most {} rows of garbage, got {}",
+                    config.board.height, rows
+                ));
+            }
+        }
+        if let Some(hole) = config.garbage.hole {
+            if hole >= config.board.width {
+                return Err(format!(
+                    "expected a garbage hole from 1 to {}, got {}",
+                    config.board.width,
+                    hole + 1
+                ));
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+fn parse_number(text: &str) -> Result<usize, String> {
+    text.parse()
+        .map_err(|_| format!("expected a number, got {}", text))
+}
+
+fn parse_ms(text: &str) -> Result<u32, String> {
+    text.parse()
+        .map_err(|_| format!("expected a time in milliseconds, got {}", text))
+}
+
+/// parses a soft drop factor of at least 1, or "sonic"
+/// for soft drop to go straight to the bottom
+fn parse_soft_drop_factor(text: &str) -> Result<Option<f32>, String> {
+    if text == "sonic" {
+        return Ok(None);
+    }
+
+    text.parse()
+        .ok()
+        .filter(|&factor: &f32| factor >= 1.)
+        .map(Some)
+        .ok_or_else(|| format!("expected a soft drop factor or sonic, got {}", text))
+}
+
+/// parses a probability from 0 to 1
+fn parse_chance(text: &str) -> Result<f32, String> {
+    text.parse()
+        .ok()
+        .filter(|chance| (0. ..=1.).contains(chance))
+        .ok_or_else(|| format!("expected a chance from 0 to 1, got {}", text))
+}
+
+/// parses a board dimension, which has to be at least `min`
+/// and small enough that positions fit in an i8
+fn parse_size(text: &str, min: i8) -> Result<i8, String> {
+    text.parse()
+        .ok()
+        .filter(|size| (min..=MAX_BOARD_SIZE).contains(size))
+        .ok_or_else(|| {
+            format!(
+                "expected a size from {} to {}, got {}",
+                min, MAX_BOARD_SIZE, text
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Result<Config, String> {
+        Config::from_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn board_size_comes_from_the_arguments() {
+        let config = args(&["--width", "12", "--height", "30", "--buffer", "10"]).unwrap();
+        assert_eq!(
+            config.board,
+            BoardSize {
+                width: 12,
+                height: 30,
+                buffer_height: 10
+            }
+        );
+        assert_eq!(args(&[]).unwrap().board, BoardSize::default());
+    }
+
+    #[test]
+    fn board_size_has_to_fit() {
+        assert!(args(&["--width", "3"]).is_err());
+        assert!(args(&["--height", "41"]).is_err());
+        assert!(args(&["--width", "wide"]).is_err());
+        assert!(args(&["--width"]).is_err());
+    }
+
+    #[test]
+    fn buffer_has_room_to_spawn_in() {
+        assert!(args(&["--buffer", "1"]).is_err());
+        assert_eq!(
+            args(&["--buffer", "2"]).unwrap().board.buffer_height,
+            MIN_BUFFER_HEIGHT
+        );
+    }
+
+    #[test]
+    fn previews_are_capped() {
+        assert_eq!(args(&["--previews", "0"]).unwrap().previews, 0);
+        assert_eq!(args(&["--previews", "12"]).unwrap().previews, MAX_PREVIEWS);
+    }
+
+    #[test]
+    fn parses_hold_modes() {
+        assert_eq!(HoldMode::parse("none"), Ok(HoldMode::Disabled));
+        assert_eq!(HoldMode::parse("unlimited"), Ok(HoldMode::Unlimited));
+        assert_eq!(HoldMode::parse("4"), Ok(HoldMode::MultiSlot(4)));
+        assert!(HoldMode::parse("0").is_err());
+        assert!(HoldMode::parse("5").is_err());
+        assert_eq!(HoldMode::MultiSlot(3).slots(), 3);
+    }
+
+    #[test]
+    fn parses_handling() {
+        let config = args(&[
+            "--das",
+            "100",
+            "--arr",
+            "0",
+            "--das-cut",
+            "20",
+            "--sdf",
+            "sonic",
+        ]);
+        assert_eq!(
+            config.unwrap().handling,
+            Handling {
+                das: 100,
+                arr: 0,
+                das_cut: 20,
+                soft_drop_factor: None,
+            }
+        );
+        assert_eq!(
+            args(&["--sdf", "40"]).unwrap().handling.soft_drop_factor,
+            Some(40.)
+        );
+        assert!(args(&["--sdf", "0.5"]).is_err());
+        assert!(args(&["--das", "-1"]).is_err());
+    }
+
+    #[test]
+    fn garbage_has_to_fit_on_the_board() {
+        assert_eq!(args(&["--garbage", "20"]).unwrap().garbage_rows, Some(20));
+        assert!(args(&["--garbage", "21"]).is_err());
+        assert!(args(&["--garbage", "25", "--height", "30"]).is_ok());
+
+        assert_eq!(
+            args(&["--garbage-hole", "10"]).unwrap().garbage.hole,
+            Some(9)
+        );
+        assert!(args(&["--garbage-hole", "11"]).is_err());
+        assert!(args(&["--garbage-hole", "15"]).is_err());
+        assert!(args(&["--garbage-hole", "15", "--width", "16"]).is_ok());
+        assert!(args(&["--garbage-hole", "0"]).is_err());
+    }
+}
@@ -4,17 +4,56 @@ pub const SCREEN_HEIGHT: f32 = 600.;
 pub const SCREEN_WIDTH: f32 = 300.;
 pub const SCREEN_WIDTHER: f32 = SCREEN_WIDTH * 1.7;
 
+// default board size, see `BoardSize`
 pub const X_SQUARES: i8 = 10;
 pub const Y_SQUARES: i8 = 20;
+pub const BUFFER_HEIGHT: i8 = 20;
+/// blocks spawn in the two rows above the board, so the
+/// buffer zone needs at least that many rows
+pub const MIN_BUFFER_HEIGHT: i8 = 2;
+pub const MAX_BOARD_SIZE: i8 = 40;
 
-pub const SQUARE_SIZE: f32 = SCREEN_HEIGHT / Y_SQUARES as f32;
+/// size of the squares in the hold and queue previews
+pub const PREVIEW_SQUARE_SIZE: f32 = 30.;
+// the side panel is split into the hold at the top, the
+// queue in the middle, and info text at the bottom
+pub const HOLD_PANEL_HEIGHT: f32 = 150.;
+pub const INFO_PANEL_Y: f32 = 450.;
 
 pub const BORDER_SIZE: f32 = 0.5;
 
-pub const TICK_INTERVAL: usize = 60;
+pub const LOCK_DELAY: usize = 30;
+pub const MAX_LOCK_RESETS: usize = 15;
+pub const ENTRY_DELAY: usize = 0;
+pub const LINE_CLEAR_DELAY: usize = 0;
 
-pub const INPUT_INTERVAL: u16 = 5;
-pub const INPUT_REPEAT_DELAY: u16 = 8;
+pub const LINES_PER_LEVEL: usize = 10;
+pub const MAX_GRAVITY: f32 = 20.;
+
+pub const PREVIEWS: usize = 3;
+pub const MAX_PREVIEWS: usize = 7;
+pub const MAX_HOLD_SLOTS: usize = 4;
+
+pub const SPRINT_LINES: usize = 40;
+pub const MARATHON_LINES: usize = 150;
+pub const MARATHON_LEVELS: usize = 15;
+pub const ULTRA_SECS: u64 = 120;
+/// seconds left in ultra when the clock turns red
+pub const ULTRA_WARNING_SECS: u64 = 10;
+pub const DIG_ROWS: usize = 10;
+pub const DIG_MESSINESS: f32 = 1.;
+
+pub const CALLOUT_DURATION: usize = 90;
+
+/// the engine steps at a fixed rate, independent of the display
+pub const FRAMES_PER_SECOND: u32 = 60;
+pub const FRAME_MS: f32 = 1000. / FRAMES_PER_SECOND as f32;
+
+// default handling in milliseconds, see `Handling`
+pub const DAS: u32 = 150;
+pub const ARR: u32 = 83;
+pub const DAS_CUT: u32 = 0;
+pub const SOFT_DROP_FACTOR: f32 = 20.;
 
 pub const TYPES: [BlockType; 7] = [
     BlockType::Line,
@@ -0,0 +1,1470 @@
+use crate::block::*;
+use crate::input::*;
+
+use std::collections::{HashMap, VecDeque};
+use std::convert::TryInto;
+use std::time::Duration;
+
+use rand::{rngs::StdRng, thread_rng, Rng, SeedableRng};
+
+use crate::config::Config;
+use crate::consts::*;
+use crate::garbage::GarbageGenerator;
+use crate::piece_queue::PieceQueue;
+use crate::scoring::{self, Clear, Score, TSpin};
+
+/// Something that happened during a call to `Engine::step`,
+/// used by frontends to react to the game without
+/// inspecting its internals
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameEvent {
+    /// the current block was placed on the board
+    Locked(BlockType),
+    /// one or more rows were cleared by the last placed block
+    LinesCleared(usize),
+    /// the current block was swapped into the hold slot
+    Held(BlockType),
+    /// points were awarded for a line clear, including any
+    /// combo or back-to-back bonus
+    Scored(usize),
+    /// a T block was spun into place, clearing `lines` rows
+    TSpin { kind: TSpin, lines: usize },
+    /// lines of garbage the last placement would send to an
+    /// opponent, including any combo or back-to-back bonus
+    Attack(usize),
+    /// the last line clear left the board empty
+    PerfectClear,
+    /// enough lines were cleared to reach a new level
+    LevelUp(usize),
+    /// rows of garbage were added to the bottom of the board
+    GarbageAdded(usize),
+    /// the game ended, for the given reason
+    GameOver(TopOut),
+}
+
+/// The ways a game can be lost
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[allow(clippy::enum_variant_names)]
+pub enum TopOut {
+    /// a new block spawned overlapping the stack
+    BlockOut,
+    /// a block locked entirely above the visible board
+    LockOut,
+    /// a block locked partly above the visible board, which
+    /// only ends the game if `partial_lock_out` is set
+    PartialLockOut,
+    /// garbage pushed the stack out of the top of the buffer zone
+    GarbageOut,
+}
+
+impl TopOut {
+    pub fn description(self) -> &'static str {
+        match self {
+            TopOut::BlockOut => "Block Out",
+            TopOut::LockOut => "Lock Out",
+            TopOut::PartialLockOut => "Partial Lock Out",
+            TopOut::GarbageOut => "Garbage Out",
+        }
+    }
+}
+
+/// Where the game is in the life of a block
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Phase {
+    /// waiting out the entry delay before the next block
+    /// spawns, with `frames` left to go
+    Spawning(usize),
+    /// the current block is in the air
+    Falling,
+    /// the current block is resting on the stack or the floor
+    Locking,
+    /// full `rows` are left on the board for `frames` more
+    /// frames before they're removed
+    LineClearing { frames: usize, rows: Vec<i8> },
+}
+
+impl Phase {
+    /// whether there's a current block that can be moved
+    pub fn active(&self) -> bool {
+        matches!(self, Phase::Falling | Phase::Locking)
+    }
+}
+
+/// The rules of the game, independent of any window or
+/// ggez Context so that it can be run headless by
+/// simulations, bots, and tests.
+///
+/// Time only advances through `step`, one frame at a time.
+#[derive(Clone)]
+pub struct Engine {
+    pub config: Config,
+    pub squares: Vec<Square>,
+    pub inputs: HashMap<InputAction, InputState>,
+    pub auto_shift: AutoShift,
+    pub current_block: Block,
+    pub phase: Phase,
+    /// rows the current block has fallen through since it last
+    /// moved down a full row, accumulated from gravity every frame
+    pub fall_progress: f32,
+    /// frames the current block has been resting on the stack
+    pub lock_timer: usize,
+    /// times the lock delay has been restarted for the current block
+    pub lock_resets: usize,
+    /// lowest row the current block has reached, reaching a new
+    /// lowest row restores all of its lock resets
+    pub lowest_y: i8,
+    /// frames left before a charged direction can shift the
+    /// current block, see `Handling::das_cut`
+    pub das_cut_timer: f32,
+    /// the rotation and index of the kick used, if the last
+    /// successful move of the current block was a rotation
+    pub last_rotation: Option<(Rotation, usize)>,
+    /// held blocks, oldest first
+    pub held_blocks: VecDeque<BlockType>,
+    /// the seed `rng` started from, which reproduces the game
+    pub seed: u64,
+    /// source of all of the game's randomness
+    pub rng: StdRng,
+    pub queue: PieceQueue,
+    pub garbage: GarbageGenerator,
+    /// rows of garbage still on the board, which are always the
+    /// bottom rows since blocks can't get underneath them
+    pub garbage_left: usize,
+    /// whether hold has been used since the last placement
+    pub used_hold: bool,
+    pub lines: usize,
+    /// blocks placed so far
+    pub pieces: usize,
+    /// inputs pressed so far, counting each press once
+    pub key_presses: usize,
+    pub level: usize,
+    pub score: Score,
+    pub game_over: bool,
+    /// frames stepped so far
+    pub frames: usize,
+    pub events: Vec<GameEvent>,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Engine::new(Config::default())
+    }
+}
+
+impl Engine {
+    pub fn new(config: Config) -> Self {
+        // makes squares a vector with capacity height * width
+        let squares = Vec::with_capacity(
+            (i16::from(config.board.width) * i16::from(config.board.height))
+                .try_into()
+                .unwrap(),
+        );
+
+        // initializes input states
+        let inputs = [
+            (InputAction::Spin, InputState::default()),
+            (InputAction::RotateCcw, InputState::default()),
+            (InputAction::Rotate180, InputState::default()),
+            (InputAction::SoftDrop, InputState::default()),
+            (InputAction::HardDrop, InputState::default()),
+            (InputAction::MoveLeft, InputState::default()),
+            (InputAction::MoveRight, InputState::default()),
+            (InputAction::Cache, InputState::default()),
+        ]
+        .iter()
+        .cloned()
+        .collect::<HashMap<InputAction, InputState>>();
+
+        let seed = config.seed.unwrap_or_else(|| thread_rng().gen());
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut queue = PieceQueue::new(config.randomizer.build(), MAX_PREVIEWS, &mut rng);
+        let garbage = GarbageGenerator::new(config.garbage, rng.gen());
+
+        // the first block comes from the queue like any other, and
+        // is spawned properly once the engine is built
+        let first_blocktype = queue.next(&mut rng);
+        let current_block = Block::spawn(first_blocktype, config.board);
+
+        let mut engine = Engine {
+            level: config.start_level,
+            seed,
+            rng,
+            config,
+            squares,
+            inputs,
+            auto_shift: AutoShift::default(),
+            lowest_y: current_block.pos.1,
+            current_block,
+            fall_progress: 0.,
+            lock_timer: 0,
+            lock_resets: 0,
+            das_cut_timer: 0.,
+            last_rotation: None,
+            held_blocks: VecDeque::with_capacity(MAX_HOLD_SLOTS + 1),
+            queue,
+            garbage,
+            garbage_left: 0,
+            used_hold: false,
+            lines: 0,
+            pieces: 0,
+            key_presses: 0,
+            score: Score::default(),
+            game_over: false,
+            phase: Phase::Falling,
+            frames: 0,
+            events: Vec::new(),
+        };
+
+        engine.add_garbage(engine.config.garbage_rows.unwrap_or(0));
+        engine.spawn(first_blocktype);
+
+        // the garbage the board starts with wasn't added during the game
+        engine.events.clear();
+        engine
+    }
+
+    /// advances the game by one frame with the given actions held down,
+    /// returning everything that happened during the frame, along with
+    /// anything since the last step like garbage added between frames
+    pub fn step(&mut self, pressed: &[InputAction]) -> Vec<GameEvent> {
+        if self.game_over {
+            return std::mem::take(&mut self.events);
+        }
+
+        self.frames += 1;
+        self.update_inputs(pressed);
+        self.das_cut_timer = (self.das_cut_timer - 1.).max(0.);
+
+        match self.phase.clone() {
+            Phase::Spawning(frames) if frames > 1 => self.phase = Phase::Spawning(frames - 1),
+            Phase::Spawning(_) => self.spawn_next(),
+            Phase::LineClearing { frames, rows } if frames > 1 => {
+                self.phase = Phase::LineClearing {
+                    frames: frames - 1,
+                    rows,
+                }
+            }
+            Phase::LineClearing { rows, .. } => {
+                self.clear_rows(&rows);
+                self.start_spawning();
+            }
+            Phase::Falling | Phase::Locking => self.update_block(),
+        }
+
+        std::mem::take(&mut self.events)
+    }
+
+    /// moves the current block according to the inputs and gravity,
+    /// and locks it once it's been resting for long enough
+    fn update_block(&mut self) {
+        self.shift();
+
+        // rotating, holding and hard dropping only happen once per
+        // press, while moving repeats according to the handling
+        let actions = [
+            InputAction::Cache,
+            InputAction::Spin,
+            InputAction::RotateCcw,
+            InputAction::Rotate180,
+            InputAction::HardDrop,
+        ];
+        for action in actions.iter() {
+            // stop once the block has locked or the game has ended
+            if self.game_over || !self.phase.active() {
+                break;
+            }
+
+            let input = self.inputs[action];
+            match action {
+                _ if !input.just_pressed() => {}
+                InputAction::Cache => self.cache(),
+                InputAction::Spin => self.spin(Rotation::Clockwise),
+                InputAction::RotateCcw => self.spin(Rotation::CounterClockwise),
+                InputAction::Rotate180 => self.spin(Rotation::Half),
+                InputAction::HardDrop => self.hard_drop(),
+                _ => {}
+            }
+        }
+
+        if self.game_over || !self.phase.active() {
+            return;
+        }
+
+        // fall by however many whole rows gravity has built up, with
+        // soft drop speeding it up or dropping straight to the bottom
+        let soft_drop = self.inputs[&InputAction::SoftDrop].down();
+        let gravity = self.config.gravity.gravity(self.level);
+        self.fall_progress += match self.config.handling.soft_drop_factor {
+            Some(factor) if soft_drop => (gravity * factor).min(MAX_GRAVITY),
+            None if soft_drop => f32::from(
+                self.current_block
+                    .max_drop(&self.squares, self.config.board),
+            ),
+            _ => gravity,
+        };
+        while self.fall_progress >= 1. {
+            self.fall_progress -= 1.;
+            if !self.try_translate(0, 1) {
+                self.fall_progress = 0.;
+            } else if soft_drop {
+                self.score.soft_drop(1);
+            }
+        }
+
+        // lock the block once it's been resting for long enough,
+        // or right away if it's used up all of its lock resets
+        if self.grounded() {
+            self.phase = Phase::Locking;
+            self.lock_timer += 1;
+            if self.lock_timer >= self.config.lock_delay
+                || self.lock_resets >= self.config.max_lock_resets
+            {
+                self.lock();
+            }
+        } else {
+            self.phase = Phase::Falling;
+            self.lock_timer = 0;
+        }
+    }
+
+    /// moves the current block once for every time the held
+    /// direction repeats this frame
+    fn shift(&mut self) {
+        if !self.auto_shift.switched() && self.das_cut_timer > 0. {
+            return;
+        }
+
+        let handling = self.config.handling;
+        self.shift_times(
+            self.auto_shift
+                .shifts(handling.das_frames(), handling.arr_frames()),
+        );
+    }
+
+    /// moves the current block in the held direction up to
+    /// `shifts` times, stopping at the first collision
+    fn shift_times(&mut self, shifts: usize) {
+        for _ in 0..shifts {
+            if !self.try_translate(self.auto_shift.direction, 0) {
+                break;
+            }
+        }
+    }
+
+    /// pushes the stack up and fills the bottom `rows` rows with
+    /// garbage, ending the game if it pushes any squares out the top.
+    /// At most a board's height of garbage is added at once
+    pub fn add_garbage(&mut self, rows: usize) {
+        if rows == 0 {
+            return;
+        }
+
+        let board = self.config.board;
+        let rise = rows.min(board.height as usize) as i8;
+        self.squares = self
+            .squares
+            .iter()
+            .map(|square| square.translate(0, -rise))
+            .collect();
+        let mut garbage = self.garbage.rows(rise as usize, board);
+        self.squares.append(&mut garbage);
+        self.garbage_left += rise as usize;
+        self.events.push(GameEvent::GarbageAdded(rise as usize));
+
+        if let Phase::LineClearing { rows, .. } = &mut self.phase {
+            rows.iter_mut().for_each(|y| *y -= rise);
+        }
+
+        // the current block moves up out of the way of the stack
+        if self.phase.active() {
+            while !self.current_block.is_valid(&self.squares, board)
+                && self.current_block.pos.1 >= -board.buffer_height
+            {
+                self.current_block = self.current_block.translate(0, -1);
+            }
+            self.lowest_y = self.lowest_y.min(self.current_block.pos.1);
+        }
+
+        if self
+            .squares
+            .iter()
+            .any(|square| square.pos.1 < -board.buffer_height)
+            || (self.phase.active() && !self.current_block.is_valid(&self.squares, board))
+        {
+            self.top_out(TopOut::GarbageOut);
+        }
+    }
+
+    /// time the game has been running for since the first block
+    /// spawned, counted in frames so that it doesn't depend on the
+    /// display or include any time spent in menus
+    pub fn time(&self) -> Duration {
+        Duration::from_secs_f64(self.frames as f64 / f64::from(FRAMES_PER_SECOND))
+    }
+
+    /// ends the game
+    fn top_out(&mut self, reason: TopOut) {
+        self.game_over = true;
+        self.events.push(GameEvent::GameOver(reason));
+    }
+
+    /// whether the current block is resting on the stack or the floor
+    pub fn grounded(&self) -> bool {
+        !self
+            .current_block
+            .translate(0, 1)
+            .is_valid(&self.squares, self.config.board)
+    }
+
+    /// places the current block on the board, clears any
+    /// full rows, and spawns the next block
+    pub fn lock(&mut self) {
+        let tspin = self.tspin();
+
+        // end the game if the block locked out of sight
+        let hidden = self
+            .current_block
+            .squares
+            .iter()
+            .filter(|square| square.pos.1 < 0)
+            .count();
+        let top_out = if hidden == self.current_block.squares.len() {
+            Some(TopOut::LockOut)
+        } else if hidden > 0 && self.config.partial_lock_out {
+            Some(TopOut::PartialLockOut)
+        } else {
+            None
+        };
+
+        // place the block on the board and set `used_hold`
+        // to false
+        self.squares
+            .append(&mut self.current_block.squares.to_vec());
+        self.used_hold = false;
+        self.pieces += 1;
+        self.events
+            .push(GameEvent::Locked(self.current_block.blocktype));
+
+        if let Some(reason) = top_out {
+            self.top_out(reason);
+            return;
+        }
+
+        // find full rows, which are cleared right away or
+        // after the line clear delay
+        let (min_y, max_y) = find_minmax(&self.current_block.squares);
+        let rows = (min_y..=max_y)
+            .filter(|&y| {
+                let row_cnt = self
+                    .squares
+                    .iter()
+                    .filter(|square| square.pos.1 == y)
+                    .count();
+                row_cnt >= self.config.board.width.try_into().unwrap()
+            })
+            .collect::<Vec<i8>>();
+        let cleared = rows.len();
+
+        if let Some(kind) = tspin {
+            self.events.push(GameEvent::TSpin {
+                kind,
+                lines: cleared,
+            });
+        }
+
+        let perfect = cleared > 0
+            && self
+                .squares
+                .iter()
+                .all(|square| rows.contains(&square.pos.1));
+        let clear = Clear {
+            lines: cleared,
+            tspin,
+            perfect,
+        };
+        let back_to_back = self.score.back_to_back;
+        let points = self.score.clear(clear, self.level);
+        if points > 0 {
+            self.events.push(GameEvent::Scored(points));
+        }
+        let attack = scoring::attack(clear, back_to_back, self.score.combo);
+        if attack > 0 {
+            self.events.push(GameEvent::Attack(attack));
+        }
+
+        if cleared > 0 {
+            self.lines += cleared;
+            self.events.push(GameEvent::LinesCleared(cleared));
+            if perfect {
+                self.events.push(GameEvent::PerfectClear);
+            }
+
+            let mut level = self.config.start_level + self.lines / self.config.lines_per_level;
+            if let Some(max_level) = self.config.max_level {
+                level = level.min(max_level);
+            }
+            if level > self.level {
+                self.level = level;
+                self.events.push(GameEvent::LevelUp(level));
+            }
+        }
+
+        if cleared > 0 && self.config.line_clear_delay > 0 {
+            self.phase = Phase::LineClearing {
+                frames: self.config.line_clear_delay,
+                rows,
+            };
+        } else {
+            self.clear_rows(&rows);
+            self.start_spawning();
+        }
+    }
+
+    /// removes full rows from top to bottom, moving the rows above them down
+    fn clear_rows(&mut self, rows: &[i8]) {
+        for &y in rows {
+            self.squares = clear_lines(&self.squares, y);
+
+            let garbage_top = i16::from(self.config.board.height) - self.garbage_left as i16;
+            if i16::from(y) >= garbage_top {
+                self.garbage_left -= 1;
+            }
+        }
+    }
+
+    /// spawns the next block, after the entry delay if there is one
+    fn start_spawning(&mut self) {
+        if self.config.entry_delay > 0 {
+            self.phase = Phase::Spawning(self.config.entry_delay);
+        } else {
+            self.spawn_next();
+        }
+    }
+
+    /// spawns the next block from the queue
+    fn spawn_next(&mut self) {
+        let blocktype = self.next_blocktype();
+        self.spawn(blocktype);
+
+        // Initial Hold System, unless the block that spawned topped out
+        if !self.game_over && self.config.ihs && self.inputs[&InputAction::Cache].down() {
+            self.cache();
+        }
+    }
+
+    /// checks whether the current block is a T that was spun into place,
+    /// using the 3-corner rule: at least three of the four corners around
+    /// its center have to be filled, and it's only a full T-spin if both
+    /// corners its point faces are filled or it used the last SRS kick
+    pub fn tspin(&self) -> Option<TSpin> {
+        if self.current_block.blocktype != BlockType::T {
+            return None;
+        }
+        let (rotation, kick) = self.last_rotation?;
+
+        let (x, y) = self.current_block.pos;
+        let filled = |(dx, dy): (i8, i8)| {
+            let pos = (x + 1 + dx, y + 1 + dy);
+            pos.0 < 0
+                || pos.0 >= self.config.board.width
+                || pos.1 >= self.config.board.height
+                || self.squares.iter().any(|square| square.pos == pos)
+        };
+
+        // corners in clockwise order starting from the top left,
+        // so the two in front of the point are next to each other
+        let corners = [(-1, -1), (1, -1), (1, 1), (-1, 1)];
+        let front = match self.current_block.orientation {
+            Orientation::Up => [0, 1],
+            Orientation::Right => [1, 2],
+            Orientation::Down => [2, 3],
+            Orientation::Left => [3, 0],
+        };
+
+        if corners.iter().filter(|&&corner| filled(corner)).count() < 3 {
+            return None;
+        }
+
+        let front_filled = front.iter().all(|&i| filled(corners[i]));
+        if front_filled || (rotation != Rotation::Half && kick == 4) {
+            Some(TSpin::Full)
+        } else {
+            Some(TSpin::Mini)
+        }
+    }
+
+    /// replaces the current block with a new one in the buffer zone
+    /// above the board, dropping it by a row right away if it can
+    pub fn spawn(&mut self, blocktype: BlockType) {
+        self.current_block = Block::spawn(blocktype, self.config.board);
+        self.phase = Phase::Falling;
+
+        // end the game if there's no room for the new block
+        if !self
+            .current_block
+            .is_valid(&self.squares, self.config.board)
+        {
+            self.top_out(TopOut::BlockOut);
+            return;
+        }
+
+        let dropped = self.current_block.translate(0, 1);
+        if dropped.is_valid(&self.squares, self.config.board) {
+            self.current_block = dropped;
+        }
+
+        self.fall_progress = 0.;
+        self.lock_timer = 0;
+        self.lock_resets = 0;
+        self.last_rotation = None;
+        self.das_cut_timer = self.config.handling.das_cut_frames();
+        self.lowest_y = self.current_block.pos.1;
+
+        // a direction charged before the block spawned shifts it right
+        // away, unless there's a DAS cut delay
+        let handling = self.config.handling;
+        if self.das_cut_timer <= 0. && self.auto_shift.charged(handling.das_frames()) {
+            self.shift_times(if handling.arr == 0 { usize::MAX } else { 1 });
+        }
+    }
+
+    /// replaces the current block after it successfully moved or rotated,
+    /// restarting the lock delay if it's resting on the stack
+    pub fn set_current_block(&mut self, block: Block) {
+        self.current_block = block;
+
+        if self.current_block.pos.1 > self.lowest_y {
+            self.lowest_y = self.current_block.pos.1;
+            self.lock_timer = 0;
+            self.lock_resets = 0;
+        } else if self.lock_timer > 0 || self.grounded() {
+            self.lock_timer = 0;
+            self.lock_resets += 1;
+        }
+    }
+
+    /// tries to translate selected block by x and y,
+    /// returning whether it moved
+    pub fn try_translate(&mut self, x: i8, y: i8) -> bool {
+        let translated = self.current_block.translate(x, y);
+        if translated.is_valid(&self.squares, self.config.board) {
+            self.set_current_block(translated);
+            self.last_rotation = None;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// takes the next blocktype from the queue
+    pub fn next_blocktype(&mut self) -> BlockType {
+        self.queue.next(&mut self.rng)
+    }
+
+    /// updates all input states given the actions held this frame
+    pub fn update_inputs(&mut self, pressed: &[InputAction]) {
+        pressed.iter().for_each(|action| {
+            self.inputs.get_mut(action).unwrap().set_pressed(true);
+        });
+
+        self.inputs.values_mut().for_each(|input_state| {
+            input_state.update();
+        });
+        self.key_presses += self
+            .inputs
+            .values()
+            .filter(|input_state| input_state.just_pressed())
+            .count();
+
+        self.auto_shift.update(
+            self.inputs[&InputAction::MoveLeft],
+            self.inputs[&InputAction::MoveRight],
+        );
+    }
+}
+
+/// find the min and max height of squares in the block,
+/// used to find filled rows
+fn find_minmax(squares: &[Square]) -> (i8, i8) {
+    squares
+        .iter()
+        .fold((i8::MAX, i8::MIN), |(min, max), current| {
+            (min.min(current.pos.1), max.max(current.pos.1))
+        })
+}
+
+fn clear_lines(squares: &[Square], row_y: i8) -> Vec<Square> {
+    squares
+        .iter()
+        .filter(|square| square.pos.1 != row_y)
+        .map(|square| {
+            if square.pos.1 < row_y {
+                square.translate(0, 1)
+            } else {
+                *square
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::config::{BoardSize, Handling, HoldMode};
+    use crate::garbage::GarbageSettings;
+    use crate::randomizer::RandomizerKind;
+
+    fn seeded(seed: u64) -> Config {
+        Config {
+            seed: Some(seed),
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn hard_drop_locks_and_spawns_the_next_block() {
+        let mut engine = Engine::new(seeded(1));
+        let blocktype = engine.current_block.blocktype;
+        let next = engine.queue.peek(1).next().unwrap();
+
+        let events = engine.step(&[InputAction::HardDrop]);
+        assert_eq!(events, vec![GameEvent::Locked(blocktype)]);
+        assert_eq!(engine.pieces, 1);
+        assert_eq!(engine.squares.len(), 4);
+        assert!(engine.squares.iter().any(|square| square.pos.1 == 19));
+        assert_eq!(engine.current_block.blocktype, next);
+
+        // holding hard drop doesn't drop the next block too
+        assert!(engine.step(&[InputAction::HardDrop]).is_empty());
+        assert_eq!(engine.pieces, 1);
+    }
+
+    #[test]
+    fn gravity_pulls_the_block_down_over_time() {
+        let mut engine = Engine::new(seeded(1));
+        let y = engine.current_block.pos.1;
+        // level 1 falls a row a second
+        for _ in 0..2 * FRAMES_PER_SECOND {
+            engine.step(&[]);
+        }
+        assert!(engine.current_block.pos.1 > y);
+        assert_eq!(engine.frames, 2 * FRAMES_PER_SECOND as usize);
+    }
+
+    #[test]
+    fn rotating_against_the_wall_kicks_off_it() {
+        let mut engine = Engine::new(seeded(1));
+        engine.current_block = Block::new(BlockType::Line, Orientation::Left).translate(-1, 5);
+
+        engine.step(&[InputAction::Spin]);
+        let block = &engine.current_block;
+        assert_eq!(block.orientation, Orientation::Up);
+        assert!(block
+            .squares
+            .iter()
+            .all(|square| (0..4).contains(&square.pos.0)));
+        assert_eq!(engine.last_rotation, Some((Rotation::Clockwise, 1)));
+    }
+
+    #[test]
+    fn each_rotation_input_turns_its_own_way() {
+        let mut engine = Engine::new(seeded(1));
+        engine.current_block = Block::new(BlockType::T, Orientation::Up).translate(3, 5);
+
+        engine.step(&[InputAction::RotateCcw]);
+        assert_eq!(engine.current_block.orientation, Orientation::Left);
+        engine.step(&[InputAction::Rotate180]);
+        assert_eq!(engine.current_block.orientation, Orientation::Right);
+
+        // holding a rotation doesn't repeat it
+        engine.step(&[InputAction::Rotate180]);
+        assert_eq!(engine.current_block.orientation, Orientation::Right);
+    }
+
+    /// puts a flat T on the floor of the board
+    fn t_on_the_floor(engine: &mut Engine) {
+        engine.current_block = Block::new(BlockType::T, Orientation::Up).translate(3, 18);
+        engine.lowest_y = engine.current_block.pos.1;
+    }
+
+    #[test]
+    fn lock_delay_waits_before_locking() {
+        let mut engine = Engine::new(seeded(1));
+        t_on_the_floor(&mut engine);
+
+        for _ in 1..LOCK_DELAY {
+            assert!(engine.step(&[]).is_empty());
+        }
+        assert_eq!(engine.phase, Phase::Locking);
+        assert_eq!(engine.step(&[]), vec![GameEvent::Locked(BlockType::T)]);
+    }
+
+    #[test]
+    fn moving_restarts_the_lock_delay() {
+        let mut engine = Engine::new(seeded(1));
+        t_on_the_floor(&mut engine);
+
+        for _ in 0..20 {
+            engine.step(&[]);
+        }
+        engine.step(&[InputAction::MoveLeft]);
+        assert_eq!(engine.lock_resets, 1);
+        for _ in 2..LOCK_DELAY {
+            engine.step(&[]);
+        }
+        assert_eq!(engine.pieces, 0);
+        engine.step(&[]);
+        assert_eq!(engine.pieces, 1);
+    }
+
+    #[test]
+    fn running_out_of_resets_locks_right_away() {
+        let mut engine = Engine::new(seeded(1));
+        t_on_the_floor(&mut engine);
+
+        for i in 0..MAX_LOCK_RESETS {
+            assert_eq!(engine.pieces, 0);
+            let direction = if i % 2 == 0 {
+                InputAction::MoveLeft
+            } else {
+                InputAction::MoveRight
+            };
+            engine.step(&[direction]);
+        }
+        assert_eq!(engine.pieces, 1);
+    }
+
+    #[test]
+    fn clearing_lines_goes_up_a_level() {
+        let mut config = seeded(1);
+        config.board.width = 4;
+        config.lines_per_level = 1;
+        let mut engine = Engine::new(config);
+        engine.spawn(BlockType::Line);
+
+        let events = engine.step(&[InputAction::HardDrop]);
+        assert!(events.contains(&GameEvent::LevelUp(2)));
+        assert_eq!(engine.level, 2);
+        assert!(engine.config.gravity.gravity(2) > engine.config.gravity.gravity(1));
+    }
+
+    #[test]
+    fn hard_drop_scores_the_rows_it_falls() {
+        let mut engine = Engine::new(seeded(1));
+        let rows = engine
+            .current_block
+            .max_drop(&engine.squares, engine.config.board);
+
+        engine.step(&[InputAction::HardDrop]);
+        assert_eq!(engine.score.points, 2 * rows as usize);
+    }
+
+    /// fills the given cells of the board with squares
+    fn fill(engine: &mut Engine, cells: impl Iterator<Item = (i8, i8)>) {
+        engine
+            .squares
+            .extend(cells.map(|(x, y)| Square::new(x, y, color(BlockType::Line))));
+    }
+
+    /// a T-spin double slot at the bottom of the board,
+    /// with the T about to rotate into it
+    fn tsd_setup() -> Engine {
+        let mut engine = Engine::new(seeded(1));
+        fill(&mut engine, (0..10).filter(|&x| x != 4).map(|x| (x, 19)));
+        fill(
+            &mut engine,
+            (0..10).filter(|x| !(3..=5).contains(x)).map(|x| (x, 18)),
+        );
+        fill(&mut engine, std::iter::once((3, 17)));
+        engine.current_block = Block::new(BlockType::T, Orientation::Right).translate(3, 17);
+        engine
+    }
+
+    #[test]
+    fn tspin_double() {
+        let mut engine = tsd_setup();
+        engine.step(&[InputAction::Spin]);
+        assert_eq!(engine.current_block.orientation, Orientation::Down);
+
+        let events = engine.step(&[InputAction::HardDrop]);
+        assert!(events.contains(&GameEvent::TSpin {
+            kind: TSpin::Full,
+            lines: 2
+        }));
+        assert!(events.contains(&GameEvent::Scored(1200)));
+        assert!(events.contains(&GameEvent::LinesCleared(2)));
+        assert_eq!(engine.lines, 2);
+        assert_eq!(engine.squares.len(), 1);
+    }
+
+    #[test]
+    fn dropping_a_t_in_isnt_a_tspin() {
+        let mut engine = tsd_setup();
+        engine.current_block = Block::new(BlockType::T, Orientation::Down).translate(3, 17);
+
+        let events = engine.step(&[InputAction::HardDrop]);
+        assert!(events.contains(&GameEvent::LinesCleared(2)));
+        assert!(events.contains(&GameEvent::Scored(300)));
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event, GameEvent::TSpin { .. })));
+    }
+
+    #[test]
+    fn emptying_the_board_is_a_perfect_clear() {
+        let mut config = seeded(1);
+        config.board.width = 4;
+        let mut engine = Engine::new(config);
+        engine.spawn(BlockType::Line);
+
+        let events = engine.step(&[InputAction::HardDrop]);
+        assert!(events.contains(&GameEvent::PerfectClear));
+        assert!(events.contains(&GameEvent::Scored(900)));
+        assert!(engine.squares.is_empty());
+    }
+
+    #[test]
+    fn leftover_squares_arent_a_perfect_clear() {
+        let mut engine = tsd_setup();
+        engine.step(&[InputAction::Spin]);
+        let events = engine.step(&[InputAction::HardDrop]);
+        assert!(!events.contains(&GameEvent::PerfectClear));
+    }
+
+    #[test]
+    fn wide_boards_spawn_in_the_middle_and_reach_the_edge() {
+        let mut config = seeded(1);
+        config.board.width = 12;
+        config.handling.das = 0;
+        config.handling.arr = 0;
+        let mut engine = Engine::new(config);
+        engine.spawn(BlockType::T);
+        let xs = |engine: &Engine| {
+            let xs = engine
+                .current_block
+                .squares
+                .iter()
+                .map(|square| square.pos.0);
+            (xs.clone().min().unwrap(), xs.max().unwrap())
+        };
+        assert_eq!(xs(&engine), (4, 6));
+
+        engine.step(&[InputAction::MoveRight]);
+        engine.step(&[InputAction::MoveRight]);
+        assert_eq!(xs(&engine), (9, 11));
+    }
+
+    #[test]
+    fn blocks_drop_a_row_as_they_spawn_if_they_can() {
+        let mut engine = Engine::new(seeded(1));
+        engine.spawn(BlockType::Line);
+        assert_eq!(engine.current_block.pos.1, -2);
+
+        fill(&mut engine, (0..10).map(|x| (x, -1)));
+        engine.spawn(BlockType::Line);
+        assert_eq!(engine.current_block.pos.1, -3);
+        assert!(!engine.game_over);
+    }
+
+    #[test]
+    fn smallest_buffer_still_fits_the_spawn() {
+        let mut config = seeded(1);
+        config.board.buffer_height = MIN_BUFFER_HEIGHT;
+        let mut engine = Engine::new(config);
+        assert!(!engine.game_over);
+        assert!(engine.step(&[]).is_empty());
+    }
+
+    #[test]
+    fn first_block_comes_from_the_queue() {
+        let config = Config {
+            randomizer: RandomizerKind::SevenBag,
+            ..seeded(3)
+        };
+        let engine = Engine::new(config);
+
+        let mut bag: Vec<BlockType> = engine.queue.peek(6).collect();
+        bag.push(engine.current_block.blocktype);
+        for &blocktype in TYPES.iter() {
+            assert!(bag.contains(&blocktype), "{:?} missing", blocktype);
+        }
+    }
+
+    #[test]
+    fn seed_decides_the_first_block() {
+        let firsts: Vec<BlockType> = (0..50)
+            .map(|seed| Engine::new(seeded(seed)).current_block.blocktype)
+            .collect();
+        for &blocktype in TYPES.iter() {
+            assert!(firsts.contains(&blocktype), "{:?} never first", blocktype);
+        }
+    }
+
+    #[test]
+    fn same_seed_plays_the_same_game() {
+        let config = Config {
+            garbage_rows: Some(5),
+            ..seeded(42)
+        };
+        let play = || {
+            let mut engine = Engine::new(config.clone());
+            let mut blocks = Vec::new();
+            for _ in 0..20 {
+                blocks.push(engine.current_block.blocktype);
+                engine.step(&[InputAction::HardDrop]);
+                engine.step(&[]);
+            }
+            let board: Vec<(i8, i8)> = engine.squares.iter().map(|square| square.pos).collect();
+            (blocks, board)
+        };
+        assert_eq!(play(), play());
+    }
+
+    fn with_hold(hold: HoldMode) -> Engine {
+        Engine::new(Config { hold, ..seeded(1) })
+    }
+
+    #[test]
+    fn classic_hold_swaps_once_per_block() {
+        let mut engine = with_hold(HoldMode::Classic);
+        let first = engine.current_block.blocktype;
+        let next = engine.queue.peek(1).next().unwrap();
+
+        assert_eq!(
+            engine.step(&[InputAction::Cache]),
+            vec![GameEvent::Held(first)]
+        );
+        assert_eq!(engine.current_block.blocktype, next);
+
+        engine.step(&[]);
+        assert!(engine.step(&[InputAction::Cache]).is_empty());
+        assert_eq!(engine.current_block.blocktype, next);
+
+        engine.step(&[InputAction::HardDrop]);
+        engine.step(&[InputAction::Cache]);
+        assert_eq!(engine.current_block.blocktype, first);
+    }
+
+    #[test]
+    fn disabled_hold_does_nothing() {
+        let mut engine = with_hold(HoldMode::Disabled);
+        let first = engine.current_block.blocktype;
+        assert!(engine.step(&[InputAction::Cache]).is_empty());
+        assert_eq!(engine.current_block.blocktype, first);
+        assert!(engine.held_blocks.is_empty());
+    }
+
+    #[test]
+    fn unlimited_hold_swaps_back_and_forth() {
+        let mut engine = with_hold(HoldMode::Unlimited);
+        let first = engine.current_block.blocktype;
+        let next = engine.queue.peek(1).next().unwrap();
+
+        for i in 0..4 {
+            engine.step(&[InputAction::Cache]);
+            engine.step(&[]);
+            let expected = if i % 2 == 0 { next } else { first };
+            assert_eq!(engine.current_block.blocktype, expected);
+        }
+    }
+
+    #[test]
+    fn multi_slot_hold_gives_back_the_oldest_block() {
+        let mut engine = with_hold(HoldMode::MultiSlot(2));
+        let mut held = Vec::new();
+
+        for _ in 0..2 {
+            held.push(engine.current_block.blocktype);
+            engine.step(&[InputAction::Cache]);
+            engine.step(&[InputAction::HardDrop]);
+        }
+        assert!(engine.held_blocks.iter().eq(held.iter()));
+
+        let current = engine.current_block.blocktype;
+        engine.step(&[]);
+        engine.step(&[InputAction::Cache]);
+        assert_eq!(engine.current_block.blocktype, held[0]);
+        assert!(engine.held_blocks.iter().eq([held[1], current].iter()));
+    }
+
+    /// drops the current block, then holds hold down while
+    /// the next one spawns after the entry delay
+    fn hold_through_entry_delay(ihs: bool) -> Vec<GameEvent> {
+        let mut engine = Engine::new(Config {
+            ihs,
+            entry_delay: 5,
+            ..seeded(1)
+        });
+        engine.step(&[InputAction::HardDrop]);
+        (0..10)
+            .flat_map(|_| engine.step(&[InputAction::Cache]))
+            .collect()
+    }
+
+    #[test]
+    fn initial_hold_holds_blocks_as_they_spawn() {
+        assert!(hold_through_entry_delay(true)
+            .iter()
+            .any(|event| matches!(event, GameEvent::Held(_))));
+        assert!(hold_through_entry_delay(false).is_empty());
+    }
+
+    #[test]
+    fn spawning_into_the_stack_is_a_block_out() {
+        let mut engine = Engine::new(seeded(1));
+        fill(&mut engine, (0..10).map(|x| (x, -2)));
+        engine.spawn(BlockType::T);
+        assert!(engine.game_over);
+        assert_eq!(engine.events, vec![GameEvent::GameOver(TopOut::BlockOut)]);
+    }
+
+    #[test]
+    fn block_out_ends_the_game_once_even_with_initial_hold() {
+        let mut engine = Engine::new(Config {
+            ihs: true,
+            ..seeded(1)
+        });
+        fill(&mut engine, (0..10).flat_map(|x| vec![(x, -2), (x, -1)]));
+        fill(&mut engine, (1..10).map(|x| (x, 0)));
+        engine.current_block = Block::new(BlockType::Line, Orientation::Left).translate(-1, 0);
+
+        // hold is already down by the time the next block spawns
+        engine.used_hold = true;
+        engine.step(&[InputAction::Cache]);
+        let events = engine.step(&[InputAction::Cache, InputAction::HardDrop]);
+        let game_overs = events
+            .iter()
+            .filter(|event| matches!(event, GameEvent::GameOver(_)))
+            .count();
+        assert_eq!(game_overs, 1);
+        assert!(events.contains(&GameEvent::GameOver(TopOut::BlockOut)));
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event, GameEvent::Held(_))));
+    }
+
+    #[test]
+    fn locking_above_the_board_tops_out() {
+        let mut engine = Engine::new(seeded(1));
+        fill(
+            &mut engine,
+            (0..20).flat_map(|y| (1..10).map(move |x| (x, y))),
+        );
+        engine.spawn(BlockType::T);
+        assert!(!engine.game_over);
+
+        let events = engine.step(&[InputAction::HardDrop]);
+        assert!(events.contains(&GameEvent::GameOver(TopOut::LockOut)));
+        assert!(engine.game_over);
+    }
+
+    /// locks a T partly above the board, on the left of a stack 19 rows tall
+    fn partly_above_the_board(partial_lock_out: bool) -> Engine {
+        let mut engine = Engine::new(Config {
+            partial_lock_out,
+            ..seeded(1)
+        });
+        fill(
+            &mut engine,
+            (1..20).flat_map(|y| (0..9).map(move |x| (x, y))),
+        );
+        engine.current_block = Block::new(BlockType::T, Orientation::Up).translate(0, -1);
+        engine.step(&[InputAction::HardDrop]);
+        engine
+    }
+
+    #[test]
+    fn partial_lock_out_is_optional() {
+        let engine = partly_above_the_board(true);
+        assert!(engine.game_over);
+        let engine = partly_above_the_board(false);
+        assert!(!engine.game_over);
+        assert_eq!(engine.pieces, 1);
+    }
+
+    fn with_handling(das: u32, arr: u32, das_cut: u32) -> Engine {
+        let mut engine = Engine::new(Config {
+            handling: Handling {
+                das,
+                arr,
+                das_cut,
+                ..Handling::default()
+            },
+            ..seeded(1)
+        });
+        engine.spawn(BlockType::T);
+        engine
+    }
+
+    fn min_x(engine: &Engine) -> i8 {
+        engine
+            .current_block
+            .squares
+            .iter()
+            .map(|square| square.pos.0)
+            .min()
+            .unwrap()
+    }
+
+    /// holds a direction for `frames` frames
+    fn hold_direction(engine: &mut Engine, action: InputAction, frames: usize) {
+        for _ in 0..frames {
+            engine.step(&[action]);
+        }
+    }
+
+    #[test]
+    fn das_waits_then_arr_zero_goes_to_the_wall() {
+        // 100ms is 6 frames
+        let mut engine = with_handling(100, 0, 0);
+        let start = min_x(&engine);
+
+        hold_direction(&mut engine, InputAction::MoveRight, 1);
+        assert_eq!(min_x(&engine), start + 1);
+        hold_direction(&mut engine, InputAction::MoveRight, 4);
+        assert_eq!(min_x(&engine), start + 1);
+        hold_direction(&mut engine, InputAction::MoveRight, 3);
+        assert_eq!(min_x(&engine), engine.config.board.width - 3);
+    }
+
+    #[test]
+    fn arr_repeats_once_das_has_charged() {
+        // 100ms is 6 frames and 50ms is 3
+        let mut engine = with_handling(100, 50, 0);
+        let start = min_x(&engine);
+
+        hold_direction(&mut engine, InputAction::MoveLeft, 5);
+        assert_eq!(min_x(&engine), start - 1);
+        hold_direction(&mut engine, InputAction::MoveLeft, 3);
+        assert_eq!(min_x(&engine), start - 2);
+        hold_direction(&mut engine, InputAction::MoveLeft, 3);
+        assert_eq!(min_x(&engine), start - 3);
+    }
+
+    #[test]
+    fn das_cut_holds_back_a_charged_direction() {
+        let mut engine = with_handling(0, 0, 0);
+        hold_direction(&mut engine, InputAction::MoveRight, 2);
+        engine.spawn(BlockType::T);
+        assert_eq!(min_x(&engine), engine.config.board.width - 3);
+
+        // 100ms is 6 frames
+        let mut engine = with_handling(0, 0, 100);
+        hold_direction(&mut engine, InputAction::MoveRight, 10);
+        engine.spawn(BlockType::T);
+        let start = min_x(&engine);
+        hold_direction(&mut engine, InputAction::MoveRight, 4);
+        assert_eq!(min_x(&engine), start);
+        hold_direction(&mut engine, InputAction::MoveRight, 4);
+        assert_eq!(min_x(&engine), engine.config.board.width - 3);
+    }
+
+    #[test]
+    fn soft_drop_speeds_up_gravity() {
+        let mut engine = Engine::new(seeded(1));
+        engine.spawn(BlockType::T);
+        let y = engine.current_block.pos.1;
+
+        // 20 times level 1 gravity is a row every 3 frames
+        for _ in 0..31 {
+            engine.step(&[InputAction::SoftDrop]);
+        }
+        let fallen = engine.current_block.pos.1 - y;
+        assert_eq!(fallen, 10);
+        assert_eq!(engine.score.points, 10);
+    }
+
+    #[test]
+    fn sonic_drop_goes_straight_down_without_locking() {
+        let mut engine = Engine::new(Config {
+            handling: Handling {
+                soft_drop_factor: None,
+                ..Handling::default()
+            },
+            ..seeded(1)
+        });
+        engine.step(&[InputAction::SoftDrop]);
+        assert!(engine.grounded());
+        assert_eq!(engine.pieces, 0);
+    }
+
+    #[test]
+    fn last_pressed_direction_wins() {
+        let mut engine = with_handling(100, 0, 0);
+        let start = min_x(&engine);
+
+        hold_direction(&mut engine, InputAction::MoveRight, 2);
+        assert_eq!(min_x(&engine), start + 1);
+
+        engine.step(&[InputAction::MoveRight, InputAction::MoveLeft]);
+        assert_eq!(min_x(&engine), start);
+        engine.step(&[InputAction::MoveRight, InputAction::MoveLeft]);
+        assert_eq!(min_x(&engine), start);
+
+        // letting go of left goes back to right
+        engine.step(&[InputAction::MoveRight]);
+        assert_eq!(min_x(&engine), start + 1);
+    }
+
+    #[test]
+    fn switching_directions_keeps_das_charged() {
+        let mut engine = with_handling(100, 0, 0);
+        hold_direction(&mut engine, InputAction::MoveRight, 8);
+        assert_eq!(min_x(&engine), engine.config.board.width - 3);
+
+        engine.step(&[InputAction::MoveRight, InputAction::MoveLeft]);
+        assert_eq!(min_x(&engine), 0);
+    }
+
+    #[test]
+    fn das_charges_during_entry_delay() {
+        let mut engine = with_handling(100, 0, 0);
+        engine.config.entry_delay = 10;
+        engine.step(&[InputAction::HardDrop]);
+        assert_eq!(engine.phase, Phase::Spawning(10));
+
+        while engine.phase != Phase::Falling {
+            engine.step(&[InputAction::MoveLeft]);
+        }
+        assert_eq!(min_x(&engine), 0);
+    }
+
+    #[test]
+    fn time_counts_frames_not_the_clock() {
+        let mut engine = Engine::new(seeded(1));
+        assert_eq!(engine.time(), Duration::from_secs(0));
+        for _ in 0..90 {
+            engine.step(&[]);
+        }
+        assert_eq!(engine.time(), Duration::from_millis(1500));
+
+        engine.frames = 120 * FRAMES_PER_SECOND as usize;
+        assert_eq!(engine.time(), Duration::from_secs(120));
+    }
+
+    #[test]
+    fn entry_delay_waits_before_spawning() {
+        let mut engine = Engine::new(Config {
+            entry_delay: 3,
+            ..seeded(1)
+        });
+        let next = engine.queue.peek(1).next().unwrap();
+        engine.step(&[InputAction::HardDrop]);
+
+        for frames in (1..=3).rev() {
+            assert_eq!(engine.phase, Phase::Spawning(frames));
+            engine.step(&[InputAction::MoveLeft]);
+        }
+        assert_eq!(engine.phase, Phase::Falling);
+        assert_eq!(engine.current_block.blocktype, next);
+    }
+
+    #[test]
+    fn line_clear_delay_leaves_full_rows_for_a_while() {
+        let mut config = seeded(1);
+        config.board.width = 4;
+        config.line_clear_delay = 2;
+        config.entry_delay = 1;
+        let mut engine = Engine::new(config);
+        engine.spawn(BlockType::Line);
+
+        let events = engine.step(&[InputAction::HardDrop]);
+        assert!(events.contains(&GameEvent::LinesCleared(1)));
+        assert_eq!(
+            engine.phase,
+            Phase::LineClearing {
+                frames: 2,
+                rows: vec![19]
+            }
+        );
+        assert_eq!(engine.squares.len(), 4);
+
+        engine.step(&[]);
+        assert_eq!(engine.squares.len(), 4);
+        engine.step(&[]);
+        assert!(engine.squares.is_empty());
+        assert_eq!(engine.phase, Phase::Spawning(1));
+        engine.step(&[]);
+        assert_eq!(engine.phase, Phase::Falling);
+    }
+
+    #[test]
+    fn clearing_garbage_counts_it_down() {
+        let config = Config {
+            board: BoardSize {
+                width: 4,
+                ..BoardSize::default()
+            },
+            garbage_rows: Some(2),
+            garbage: GarbageSettings {
+                hole: Some(0),
+                messiness: None,
+            },
+            ..seeded(1)
+        };
+        let mut engine = Engine::new(config);
+        assert_eq!(engine.garbage_left, 2);
+        assert_eq!(engine.squares.len(), 6);
+
+        engine.current_block = Block::new(BlockType::Line, Orientation::Left).translate(-1, 0);
+        let events = engine.step(&[InputAction::HardDrop]);
+        assert!(!events.contains(&GameEvent::GarbageAdded(2)));
+        assert!(events.contains(&GameEvent::LinesCleared(2)));
+        assert_eq!(engine.garbage_left, 0);
+        assert_eq!(engine.squares.len(), 2);
+    }
+
+    #[test]
+    fn garbage_pushes_the_stack_and_block_up() {
+        let mut engine = Engine::new(seeded(1));
+        fill(&mut engine, std::iter::once((0, 19)));
+        engine.current_block = Block::new(BlockType::T, Orientation::Up).translate(3, 17);
+
+        // the block only moves up as far as it has to
+        engine.add_garbage(3);
+        assert!(engine.squares.iter().any(|square| square.pos == (0, 16)));
+        assert_eq!(engine.squares.len(), 1 + 3 * 9);
+        assert_eq!(engine.current_block.pos, (3, 15));
+        assert_eq!(engine.garbage_left, 3);
+        assert_eq!(engine.step(&[])[0], GameEvent::GarbageAdded(3));
+    }
+
+    #[test]
+    fn too_much_garbage_tops_out() {
+        let mut engine = Engine::new(seeded(1));
+        let mut added = 0;
+        while !engine.game_over {
+            engine.add_garbage(20);
+            added += 1;
+        }
+
+        let events = engine.step(&[]);
+        assert_eq!(events.len(), added + 1);
+        assert_eq!(
+            events.last(),
+            Some(&GameEvent::GameOver(TopOut::GarbageOut))
+        );
+        assert!(engine.step(&[]).is_empty());
+    }
+
+    #[test]
+    fn clears_report_their_attack() {
+        let mut engine = tsd_setup();
+        engine.step(&[InputAction::Spin]);
+        let events = engine.step(&[InputAction::HardDrop]);
+        assert!(events.contains(&GameEvent::Attack(4)));
+
+        // singles don't send anything
+        let mut config = seeded(1);
+        config.board.width = 4;
+        let mut engine = Engine::new(config);
+        fill(&mut engine, std::iter::once((0, 19)));
+        engine.spawn(BlockType::Line);
+        let events = engine.step(&[InputAction::HardDrop]);
+        assert!(events.contains(&GameEvent::LinesCleared(1)));
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event, GameEvent::Attack(_))));
+    }
+}
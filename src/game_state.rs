@@ -1,123 +1,160 @@
 use crate::block::*;
+use crate::config::{BoardSize, Config};
+use crate::engine::{Engine, GameEvent, Phase};
 use crate::input::*;
 
-use std::cell::RefCell;
-use std::collections::HashMap;
-use std::convert::TryInto;
-use std::rc::Rc;
-
-use rand::seq::SliceRandom;
-use rand::thread_rng;
-
 use crate::consts::*;
 use crate::main_state::{Signal, SignalState, StateTrait};
-use crate::menu_state::GameOverData;
+use crate::menu_state::{GameOverData, Outcome};
+use crate::mode::{GameMode, ModeKind};
+use crate::scoring::TSpin;
 
 use ggez::{
     event::EventHandler,
-    graphics::{self, Color, DrawMode, DrawParam, Font, MeshBuilder, Scale, Text, TextFragment},
+    graphics::{
+        self, Color, DrawMode, DrawParam, Font, MeshBuilder, Rect, Scale, Text, TextFragment,
+    },
     input::keyboard::KeyCode,
     timer, Context, GameResult,
 };
 
-#[derive(Clone)]
+/// Where the board is drawn on screen, scaled so that
+/// any board size fits left of the side panel
+#[derive(Clone, Copy, Debug)]
+pub struct BoardLayout {
+    pub x: f32,
+    pub y: f32,
+    pub square_size: f32,
+}
+
+impl BoardLayout {
+    pub fn new(size: BoardSize) -> Self {
+        let width = f32::from(size.width);
+        let height = f32::from(size.height);
+        let square_size = (SCREEN_WIDTH / width).min(SCREEN_HEIGHT / height);
+
+        // centered horizontally, and resting on the bottom of the screen
+        BoardLayout {
+            x: (SCREEN_WIDTH - square_size * width) / 2.,
+            y: SCREEN_HEIGHT - square_size * height,
+            square_size,
+        }
+    }
+}
+
+/// Drives an `Engine` with keyboard input and renders it
 pub struct GameState {
-    pub squares: Vec<Square>,
-    pub inputs: Rc<RefCell<HashMap<InputAction, InputState>>>,
-    pub current_block: Block,
-    pub update_timer: usize,
-    pub held_block: Option<BlockType>,
-    pub queue: [usize; 14],
-    pub block_index: usize,
-    pub used_hold: bool,
-    pub queued_queue: [usize; 14],
-    pub lines: usize,
+    pub engine: Engine,
+    pub mode_kind: ModeKind,
+    pub mode: Box<dyn GameMode>,
+    pub layout: BoardLayout,
     pub font: Font,
     pub info_text: Text,
+    /// text announcing a special clear, shown over the board
+    /// until `callout_timer` runs out
+    pub callout: Option<Text>,
+    pub callout_timer: usize,
     pub signals: Vec<Signal>,
 }
 
-pub fn generate_queue() -> [usize; 14] {
-    let mut rng = thread_rng();
+impl GameState {
+    pub fn new(font: Font, mut config: Config, mode_kind: ModeKind) -> Self {
+        let mode = mode_kind.build();
+        mode.configure(&mut config);
+
+        let mut game_state = GameState {
+            layout: BoardLayout::new(config.board),
+            engine: Engine::new(config),
+            mode_kind,
+            mode,
+            info_text: Text::new(""),
+            callout: None,
+            callout_timer: 0,
+            font,
+            signals: Vec::new(),
+        };
+        game_state.update_info_text();
+        game_state
+    }
 
-    //generates an iterator [0, 1, 2, 3, 4, 5, 6, 0, 1, 2, 3, 4, 5, 6]
-    let mut slice = (0..=6).cycle().take(14).collect::<Vec<usize>>();
-    slice.shuffle(&mut rng);
-    slice.as_slice().try_into().expect("error generating queue")
-}
+    /// rebuilds the info panel text from the engine
+    pub fn update_info_text(&mut self) {
+        let engine = &self.engine;
+        let mut info = self.mode.hud(engine).join("\n");
 
-impl GameState {
-    pub fn new(font: Font) -> Self {
-        // makes squares a vector with capacity height * width
-        let squares = Vec::with_capacity(
-            (i16::from(X_SQUARES) * i16::from(Y_SQUARES))
-                .try_into()
-                .unwrap(),
-        );
+        // combos and back-to-backs share a line to fit in the panel
+        let mut streaks = Vec::new();
+        if engine.score.back_to_back {
+            streaks.push("B2B".to_string());
+        }
+        if let Some(combo) = engine.score.combo.filter(|&combo| combo > 0) {
+            streaks.push(format!("Combo {}", combo));
+        }
+        if !streaks.is_empty() {
+            info.push('\n');
+            info.push_str(&streaks.join("  "));
+        }
 
-        // creates current block at top center of board
-        let current_block =
-            Block::new(BlockType::Line, Orientation::Up).translate(X_SQUARES as i8 / 2, 0);
-
-        // initializes input states
-        let inputs = [
-            (InputAction::Spin, InputState::default()),
-            (InputAction::SoftDrop, InputState::default()),
-            (InputAction::HardDrop, InputState::default()),
-            (InputAction::MoveLeft, InputState::default()),
-            (InputAction::MoveRight, InputState::default()),
-            (InputAction::Cache, InputState::default()),
-        ]
-        .iter()
-        .cloned()
-        .collect::<HashMap<InputAction, InputState>>();
-
-        let info_text = Text::new(
-            TextFragment::new("Lines: 0 \n 0:00")
-                .font(font)
-                .scale(Scale::uniform(24.0)),
+        let color = if self.mode.warning(engine) {
+            Color::new(1.0, 0.0, 0.0, 1.0)
+        } else {
+            Color::new(1.0, 1.0, 1.0, 1.0)
+        };
+        self.info_text = Text::new(
+            TextFragment::new(info)
+                .font(self.font)
+                .color(color)
+                .scale(Scale::uniform(22.0)),
         );
+    }
 
-        GameState {
-            squares,
-            inputs: Rc::new(RefCell::new(inputs)),
-            current_block,
-            update_timer: 0,
-            held_block: None,
-            queue: generate_queue(),
-            block_index: 0,
-            used_hold: false,
-            queued_queue: generate_queue(),
-            lines: 0,
-            info_text,
-            font,
-            signals: Vec::new(),
-        }
+    /// shows `text` over the board for a while
+    pub fn show_callout(&mut self, text: &str) {
+        self.callout = Some(Text::new(
+            TextFragment::new(text)
+                .font(self.font)
+                .scale(Scale::uniform(32.0)),
+        ));
+        self.callout_timer = CALLOUT_DURATION;
     }
 
-    /// tries to translate selected block by x and y
-    pub fn try_translate(&mut self, x: i8, y: i8) {
-        let translated = self.current_block.translate(x, y);
-        if translated.is_valid(&self.squares) {
-            self.current_block = translated;
+    /// reacts to the events from a step of the engine
+    fn handle_events(&mut self, events: &[GameEvent]) {
+        let tspin = events
+            .iter()
+            .any(|event| matches!(event, GameEvent::TSpin { .. }));
+        for event in events.iter() {
+            match event {
+                GameEvent::GameOver(reason) => self.end_game(Outcome::ToppedOut(*reason)),
+                GameEvent::TSpin { kind, lines } => {
+                    self.show_callout(&tspin_name(*kind, *lines));
+                }
+                GameEvent::LinesCleared(4) if !tspin => self.show_callout("Tetris"),
+                GameEvent::PerfectClear => self.show_callout("Perfect Clear"),
+                _ => {}
+            }
         }
     }
 
-    /// swaps current queue with next queue
-    /// I used two queues because the graphics need to be continuous
-    pub fn update_queue(&mut self) {
-        let mut rng = thread_rng();
-        self.queue = self.queued_queue;
-        self.queued_queue.shuffle(&mut rng);
+    /// goes back to the menu with the results of the game
+    fn end_game(&mut self, outcome: Outcome) {
+        self.signals.push(Signal::EndGame(GameOverData {
+            mode: self.mode_kind,
+            outcome,
+            results: self.mode.results(&self.engine),
+            seed: self.engine.seed,
+        }));
     }
 
-    /// updates all input states
-    pub fn update_inputs(&mut self, ctx: &Context) {
+    /// maps the currently pressed keys to input actions
+    pub fn pressed_actions(&self, ctx: &Context) -> Vec<InputAction> {
         use ggez::input::keyboard::pressed_keys;
-        pressed_keys(&ctx)
+        pressed_keys(ctx)
             .iter()
             .filter_map(|keycode| match keycode {
                 KeyCode::Up | KeyCode::W => Some(InputAction::Spin),
+                KeyCode::Z | KeyCode::Q => Some(InputAction::RotateCcw),
+                KeyCode::E => Some(InputAction::Rotate180),
                 KeyCode::Left | KeyCode::A => Some(InputAction::MoveLeft),
                 KeyCode::Right | KeyCode::D => Some(InputAction::MoveRight),
                 KeyCode::Down | KeyCode::S => Some(InputAction::SoftDrop),
@@ -125,194 +162,174 @@ impl GameState {
                 KeyCode::Space => Some(InputAction::HardDrop),
                 _ => None,
             })
-            .for_each(|action| {
-                self.inputs
-                    .borrow_mut()
-                    .get_mut(&action)
-                    .unwrap()
-                    .set_pressed(true);
-            });
-
-        self.inputs
-            .borrow_mut()
-            .values_mut()
-            .for_each(|input_state| {
-                input_state.update();
-            });
+            .collect()
     }
 }
 
 impl EventHandler for GameState {
     fn update(&mut self, ctx: &mut Context) -> GameResult {
-        self.update_timer += 1;
-
-        self.update_inputs(ctx);
-        self.inputs
-            .clone()
-            .borrow()
-            .iter()
-            .for_each(|(action, input_state)| {
-                if input_state.repeated(INPUT_REPEAT_DELAY, INPUT_INTERVAL) {
-                    match *action {
-                        InputAction::MoveLeft => self.try_translate(-1, 0),
-                        InputAction::MoveRight => self.try_translate(1, 0),
-                        InputAction::SoftDrop => self.try_translate(0, 1),
-                        InputAction::HardDrop => self.hard_drop(),
-                        InputAction::Cache => self.cache(),
-                        InputAction::Spin => self.spin(),
-                    }
-                }
-            });
-
-        // only update on ticks
-        if self.update_timer >= TICK_INTERVAL {
-            self.update_timer = 0;
-
-            // create a temporary block that is the current_block translated down by one
-            let translated = self.current_block.translate(0, 1);
-
-            if translated.is_valid(&self.squares) {
-                self.current_block = translated;
+        let pressed = self.pressed_actions(ctx);
+
+        // step the engine at a fixed rate, catching up on any frames
+        // missed since the last update, so that games play out the
+        // same however often they're drawn
+        let mut stepped = false;
+        while timer::check_update_time(ctx, FRAMES_PER_SECOND) {
+            if self.callout_timer > 0 {
+                self.callout_timer -= 1;
             } else {
-                // update the queue if it's at the end
-                if self.block_index == 14 {
-                    self.update_queue();
-                    self.block_index = 0;
-                }
-
-                let blocktype = TYPES[self.queue[self.block_index]];
-                self.block_index += 1;
-
-                // if any of the squares are over the top of the screen,
-                // end the game
-                if self
-                    .current_block
-                    .squares
-                    .iter()
-                    .any(|square| square.pos.1 < 0)
-                {
-                    self.signals.push(Signal::EndGame(GameOverData {
-                        lines: self.lines,
-                        time: timer::time_since_start(ctx),
-                    }));
-                };
+                self.callout = None;
+            }
 
-                // since the block is not valid, it is colliding,
-                // so place it on the board and set `used_hold`
-                // to false
-                self.squares
-                    .append(&mut self.current_block.squares.to_vec());
-                self.used_hold = false;
-
-                // find and clear full rows or end the game at 40 lines
-                {
-                    let (min_y, max_y) = find_minmax(&self.current_block.squares);
-
-                    (min_y..max_y + 1).for_each(|y| {
-                        let row_cnt = self
-                            .squares
-                            .iter()
-                            .filter(|square| square.pos.1 == y)
-                            .count();
-
-                        if row_cnt >= X_SQUARES.try_into().unwrap() {
-                            self.lines += 1;
-
-                            self.squares = clear_lines(&self.squares, y);
-                        }
-                    });
-                }
+            let events = self.engine.step(&pressed);
+            self.handle_events(&events);
+            self.mode.handle_events(&events);
+            if !self.engine.game_over && self.mode.finished(&self.engine) {
+                self.end_game(Outcome::Cleared);
+            }
+            stepped = true;
 
-                // reset the current block
-                self.current_block = Block::new(blocktype, Orientation::Up)
-                    .translate(X_SQUARES / 2, 0)
-                    .translate(0, -5);
+            // stop once the game has ended
+            if !self.signals.is_empty() {
+                break;
             }
         }
+
+        // the info panel can have a clock down to the millisecond,
+        // so it's kept up to date every frame
+        if stepped {
+            self.update_info_text();
+        }
+
         Ok(())
     }
 
     fn draw(&mut self, ctx: &mut Context) -> GameResult {
         graphics::clear(ctx, graphics::Color::new(0.0, 0.0, 0.0, 1.0));
 
-        if ggez::timer::ticks(ctx) % 60 == 0 {
-            self.info_text = Text::new(
-                TextFragment::new(format! {"Lines: {}\n{}", self.lines, duration_display(timer::time_since_start(ctx))})
-                .font(self.font)
-                .scale(Scale::uniform(24.0)),
-            );
-        }
-
         graphics::draw(
             ctx,
             &self.info_text,
-            DrawParam::new().dest([SCREEN_WIDTH + 25., 10.]),
+            DrawParam::new().dest([SCREEN_WIDTH + 25., INFO_PANEL_Y + 5.]),
         )
         .expect("Error drawing info text");
 
+        let engine = &self.engine;
+        let layout = self.layout;
         let mut mesh = MeshBuilder::new();
 
-        self.current_block.squares.iter().for_each(|square| {
-            mesh.rectangle(DrawMode::fill(), square.rect, square.color);
-        });
-
-        self.squares.iter().for_each(|square| {
-            mesh.rectangle(DrawMode::fill(), square.rect, square.color);
-        });
-
-        {
-            let preview = self
-                .current_block
-                .translate(0, self.current_block.max_drop(&self.squares));
-
-            preview.squares.iter().for_each(|square| {
+        // squares in the buffer zone above the board aren't drawn
+        let draw_square = |mesh: &mut MeshBuilder, pos: (i8, i8), color: Color| {
+            if pos.1 >= 0 {
                 mesh.rectangle(
                     DrawMode::fill(),
-                    square.rect,
-                    Color::new(1.0, 1.0, 1.0, 0.5),
+                    square_rect([layout.x, layout.y], layout.square_size, pos),
+                    color,
                 );
+            }
+        };
+
+        // rows waiting to be cleared flash white
+        let clearing: &[i8] = match &engine.phase {
+            Phase::LineClearing { rows, .. } => rows,
+            _ => &[],
+        };
+        engine.squares.iter().for_each(|square| {
+            if clearing.contains(&square.pos.1) {
+                draw_square(&mut mesh, square.pos, Color::new(1.0, 1.0, 1.0, 1.0));
+            } else {
+                draw_square(&mut mesh, square.pos, square.color);
+            }
+        });
+
+        // there's no current block between one locking and the next spawning
+        if engine.phase.active() {
+            engine.current_block.squares.iter().for_each(|square| {
+                draw_square(&mut mesh, square.pos, square.color);
             });
-        }
 
-        for i in self.block_index..(self.block_index + 3) {
-            let future_type = if i < 14 {
-                TYPES[self.queue[i]]
-            } else {
-                TYPES[self.queued_queue[i - 13]]
-            };
-            let future_block = Block::new(future_type, Orientation::Up).translate(
-                X_SQUARES + 2,
-                (6 + 5 * (i - self.block_index)).try_into().unwrap(),
+            let preview = engine.current_block.translate(
+                0,
+                engine
+                    .current_block
+                    .max_drop(&engine.squares, engine.config.board),
             );
 
-            future_block.squares.iter().for_each(|square| {
-                mesh.rectangle(DrawMode::fill(), square.rect, color(future_block.blocktype));
+            preview.squares.iter().for_each(|square| {
+                draw_square(&mut mesh, square.pos, Color::new(1.0, 1.0, 1.0, 0.5));
             });
         }
 
-        if let Some(held_type) = self.held_block {
-            let held = match held_type {
-                BlockType::Line => {
-                    Block::new(BlockType::Line, Orientation::Left).translate(X_SQUARES + 1, 3)
-                }
-                BlockType::Square => {
-                    Block::new(BlockType::Square, Orientation::Up).translate(X_SQUARES + 3, 2)
-                }
-                BlockType::T => {
-                    Block::new(BlockType::T, Orientation::Up).translate(X_SQUARES + 2, 2)
-                }
-                _ => Block::new(held_type, Orientation::Up).translate(X_SQUARES + 3, 2),
-            };
+        // the queue gets the space between the hold and the info text,
+        // split evenly between the previews and shrunk to fit if needed
+        let panel_center = SCREEN_WIDTH + (SCREEN_WIDTHER - SCREEN_WIDTH) / 2.;
+        let previews = engine.config.previews;
+        if previews > 0 {
+            let slot_height = (INFO_PANEL_Y - HOLD_PANEL_HEIGHT) / previews as f32;
+            let size = PREVIEW_SQUARE_SIZE.min(slot_height / 3.);
+
+            for (i, future_type) in engine.queue.peek(previews).enumerate() {
+                let center = [
+                    panel_center,
+                    HOLD_PANEL_HEIGHT + slot_height * (i as f32 + 0.5),
+                ];
+                draw_preview(&mut mesh, future_type, center, size, color(future_type));
+            }
+        }
 
-            held.squares.iter().for_each(|square| {
-                mesh.rectangle(DrawMode::fill(), square.rect, color(held_type));
-            });
+        // held blocks are greyed out while hold can't be used
+        let hold_slots = engine.config.hold.slots();
+        if hold_slots > 0 {
+            let slot_height = HOLD_PANEL_HEIGHT / hold_slots as f32;
+            let size = PREVIEW_SQUARE_SIZE.min(slot_height / 3.);
+
+            for (i, &held_type) in engine.held_blocks.iter().enumerate() {
+                let center = [panel_center, slot_height * (i as f32 + 0.5)];
+                let held_color = if engine.used_hold {
+                    Color::new(0.5, 0.5, 0.5, 1.0)
+                } else {
+                    color(held_type)
+                };
+                draw_preview(&mut mesh, held_type, center, size, held_color);
+            }
+        }
+
+        // outline the board when it doesn't fill the space left of the panel
+        let board_right = SCREEN_WIDTH - layout.x;
+        if layout.x > 0. {
+            mesh.line(
+                &[[layout.x, layout.y], [layout.x, SCREEN_HEIGHT]],
+                2.,
+                Color::new(1.0, 1.0, 1.0, 1.0),
+            )
+            .unwrap();
+            mesh.line(
+                &[[board_right, layout.y], [board_right, SCREEN_HEIGHT]],
+                2.,
+                Color::new(1.0, 1.0, 1.0, 1.0),
+            )
+            .unwrap();
+        }
+        if layout.y > 0. {
+            mesh.line(
+                &[[layout.x, layout.y], [board_right, layout.y]],
+                2.,
+                Color::new(1.0, 1.0, 1.0, 1.0),
+            )
+            .unwrap();
         }
 
+        mesh.line(
+            &[[SCREEN_WIDTH, 0.], [SCREEN_WIDTH, SCREEN_HEIGHT]],
+            2.,
+            Color::new(1.0, 1.0, 1.0, 1.0),
+        )
+        .unwrap();
+
         mesh.line(
             &[
-                [SCREEN_WIDTH as f32, 0.],
-                [SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32],
+                [SCREEN_WIDTH, HOLD_PANEL_HEIGHT],
+                [SCREEN_WIDTHER, HOLD_PANEL_HEIGHT],
             ],
             2.,
             Color::new(1.0, 1.0, 1.0, 1.0),
@@ -320,7 +337,7 @@ impl EventHandler for GameState {
         .unwrap();
 
         mesh.line(
-            &[[SCREEN_WIDTH as f32, 150.], [SCREEN_WIDTHER as f32, 150.]],
+            &[[SCREEN_WIDTH, INFO_PANEL_Y], [SCREEN_WIDTHER, INFO_PANEL_Y]],
             2.,
             Color::new(1.0, 1.0, 1.0, 1.0),
         )
@@ -329,6 +346,13 @@ impl EventHandler for GameState {
         let mesh = &mesh.build(ctx).unwrap();
 
         graphics::draw(ctx, mesh, DrawParam::new()).unwrap();
+
+        if let Some(callout) = &self.callout {
+            let x = (SCREEN_WIDTH - callout.width(ctx) as f32) / 2.;
+            graphics::draw(ctx, callout, DrawParam::new().dest([x, SCREEN_HEIGHT / 3.]))
+                .expect("Error drawing callout");
+        }
+
         graphics::present(ctx).expect("error rendering");
 
         Ok(())
@@ -343,40 +367,62 @@ impl SignalState for GameState {
 
 impl StateTrait for GameState {}
 
-/// find the min and max height of squares in the block,
-/// used to find filled rows
-fn find_minmax(squares: &[Square]) -> (i8, i8) {
-    squares.iter().fold((0, Y_SQUARES), |(min, max), current| {
-        let current_y = current.pos.1;
-        if current_y < min {
-            (current_y, max)
-        } else if current_y > max {
-            (min, current_y)
-        } else {
-            (min, max)
-        }
-    })
+/// the rectangle for a square at `pos` in a grid
+/// of squares with the given size starting at `origin`
+fn square_rect(origin: [f32; 2], size: f32, pos: (i8, i8)) -> Rect {
+    Rect::new(
+        origin[0] + f32::from(pos.0) * size + BORDER_SIZE,
+        origin[1] + f32::from(pos.1) * size + BORDER_SIZE,
+        size - (BORDER_SIZE * 2.),
+        size - (BORDER_SIZE * 2.),
+    )
 }
 
-fn clear_lines(squares: &[Square], row_y: i8) -> Vec<Square> {
-    squares
-        .iter()
-        .filter(|square| square.pos.1 != row_y)
-        .map(|square| {
-            if square.pos.1 < row_y {
-                square.translate(0, 1)
-            } else {
-                *square
-            }
-        })
-        .collect()
+/// draws a block in its spawn orientation, centered
+/// on `center`, for the hold and queue previews
+fn draw_preview(
+    mesh: &mut MeshBuilder,
+    blocktype: BlockType,
+    center: [f32; 2],
+    size: f32,
+    color: Color,
+) {
+    let block = Block::new(blocktype, Orientation::Up);
+    let xs = block.squares.iter().map(|square| square.pos.0);
+    let ys = block.squares.iter().map(|square| square.pos.1);
+    let (min_x, max_x) = (
+        f32::from(xs.clone().min().unwrap()),
+        f32::from(xs.max().unwrap()),
+    );
+    let (min_y, max_y) = (
+        f32::from(ys.clone().min().unwrap()),
+        f32::from(ys.max().unwrap()),
+    );
+
+    let origin = [
+        center[0] - (min_x + max_x + 1.) * size / 2.,
+        center[1] - (min_y + max_y + 1.) * size / 2.,
+    ];
+    block.squares.iter().for_each(|square| {
+        mesh.rectangle(
+            DrawMode::fill(),
+            square_rect(origin, size, square.pos),
+            color,
+        );
+    });
 }
 
-fn duration_display(duration: std::time::Duration) -> String {
-    let (mins, secs) = (duration.as_secs() / 60, duration.as_secs() % 60);
-    if secs < 10 {
-        format!("{}:0{}", mins, secs)
-    } else {
-        format!("{}:{}", mins, secs)
+/// the name of a T-spin, e.g. "T-Spin Mini Single"
+fn tspin_name(kind: TSpin, lines: usize) -> String {
+    let mut name = match kind {
+        TSpin::Full => "T-Spin".to_string(),
+        TSpin::Mini => "T-Spin Mini".to_string(),
+    };
+    match lines {
+        1 => name.push_str(" Single"),
+        2 => name.push_str(" Double"),
+        3 => name.push_str(" Triple"),
+        _ => {}
     }
+    name
 }
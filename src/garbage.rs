@@ -0,0 +1,112 @@
+use crate::block::Square;
+use crate::config::BoardSize;
+
+use ggez::graphics::Color;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// How the holes in garbage rows are placed
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GarbageSettings {
+    /// column of the first hole, picked at random if None
+    pub hole: Option<i8>,
+    /// chance from 0 to 1 that the hole moves to another column
+    /// between one row and the next, never moving it if None
+    pub messiness: Option<f32>,
+}
+
+/// Makes rows of garbage, each full apart from one hole. It has
+/// its own rng so that garbage doesn't change the order of blocks
+#[derive(Clone)]
+pub struct GarbageGenerator {
+    pub settings: GarbageSettings,
+    /// column of the hole in the last row made
+    pub hole: Option<i8>,
+    pub rng: StdRng,
+}
+
+impl GarbageGenerator {
+    pub fn new(settings: GarbageSettings, seed: u64) -> Self {
+        GarbageGenerator {
+            settings,
+            hole: None,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    /// squares for `rows` rows of garbage at the bottom of the board,
+    /// for once the stack has been moved up to make room for them
+    pub fn rows(&mut self, rows: usize, size: BoardSize) -> Vec<Square> {
+        let mut squares = Vec::with_capacity(rows * size.width as usize);
+
+        for row in 0..rows {
+            let hole = self.next_hole(size.width);
+            let y = size.height - 1 - row as i8;
+            squares.extend(
+                (0..size.width)
+                    .filter(|&x| x != hole)
+                    .map(|x| Square::new(x, y, Color::new(0.5, 0.5, 0.5, 1.0))),
+            );
+        }
+
+        squares
+    }
+
+    /// keeps the hole where it was, unless it's the first row or
+    /// the messiness moves it to a different column
+    fn next_hole(&mut self, width: i8) -> i8 {
+        let hole = match self.hole {
+            None => self
+                .settings
+                .hole
+                .filter(|hole| (0..width).contains(hole))
+                .unwrap_or_else(|| self.rng.gen_range(0, width)),
+            Some(hole) if self.rng.gen::<f32>() < self.settings.messiness.unwrap_or(0.) => {
+                (hole + self.rng.gen_range(1, width)) % width
+            }
+            Some(hole) => hole,
+        };
+
+        self.hole = Some(hole);
+        hole
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn holes(settings: GarbageSettings, seed: u64) -> Vec<i8> {
+        let size = BoardSize::default();
+        let mut generator = GarbageGenerator::new(settings, seed);
+        let squares = generator.rows(size.height as usize, size);
+        assert_eq!(squares.len(), 20 * 9);
+
+        (0..size.height)
+            .map(|y| {
+                (0..size.width)
+                    .find(|&x| !squares.iter().any(|square| square.pos == (x, y)))
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn holes_stay_put_without_messiness() {
+        let settings = GarbageSettings {
+            hole: Some(3),
+            messiness: None,
+        };
+        assert!(holes(settings, 1).iter().all(|&hole| hole == 3));
+    }
+
+    #[test]
+    fn full_messiness_moves_the_hole_every_row() {
+        let settings = GarbageSettings {
+            hole: None,
+            messiness: Some(1.),
+        };
+        let holes = holes(settings, 1);
+        assert!(holes.windows(2).all(|pair| pair[0] != pair[1]));
+        assert_eq!(holes, self::holes(settings, 1));
+    }
+}
@@ -0,0 +1,121 @@
+use crate::consts::*;
+
+/// How fast blocks fall at each level, in G (rows per frame)
+///
+/// Curves are plain data so that different games can
+/// ship their own, either from the presets or from a list
+#[derive(Clone, Debug, PartialEq)]
+pub struct GravityCurve {
+    /// gravity from level 1 onwards, the last entry
+    /// is used for every level past the end
+    pub levels: Vec<f32>,
+}
+
+impl Default for GravityCurve {
+    fn default() -> Self {
+        GravityCurve::guideline()
+    }
+}
+
+impl GravityCurve {
+    /// a custom curve, with each level capped at `MAX_GRAVITY`
+    pub fn new(levels: Vec<f32>) -> Self {
+        GravityCurve {
+            levels: levels.iter().map(|g| g.clamp(0., MAX_GRAVITY)).collect(),
+        }
+    }
+
+    /// the curve from the Tetris guideline, where a row takes
+    /// (0.8 - (level - 1) * 0.007)^(level - 1) seconds to fall
+    pub fn guideline() -> Self {
+        GravityCurve::new(
+            (1..=20)
+                .map(|level| {
+                    let seconds_per_row = (0.8 - (level - 1) as f32 * 0.007).powi(level - 1);
+                    1. / (seconds_per_row * 60.)
+                })
+                .collect(),
+        )
+    }
+
+    /// the curve from NES Tetris, where NES level 0 is level 1
+    #[rustfmt::skip]
+    pub fn nes() -> Self {
+        // frames per row for each level
+        let frames: [u8; 30] = [
+            48, 43, 38, 33, 28, 23, 18, 13, 8, 6,
+            5, 5, 5, 4, 4, 4, 3, 3, 3, 2,
+            2, 2, 2, 2, 2, 2, 2, 2, 2, 1,
+        ];
+        GravityCurve::new(frames.iter().map(|&f| 1. / f32::from(f)).collect())
+    }
+
+    /// parses a preset name or a comma separated list of gravities
+    pub fn parse(text: &str) -> Result<Self, String> {
+        match text {
+            "guideline" => Ok(GravityCurve::guideline()),
+            "nes" => Ok(GravityCurve::nes()),
+            _ => text
+                .split(',')
+                .map(|g| {
+                    g.trim()
+                        .parse::<f32>()
+                        .map_err(|_| format!("invalid gravity: {}", g))
+                })
+                .collect::<Result<Vec<f32>, String>>()
+                .map(GravityCurve::new),
+        }
+    }
+
+    /// gravity at the given level, counting from 1
+    pub fn gravity(&self, level: usize) -> f32 {
+        let index = level.max(1) - 1;
+        self.levels
+            .get(index)
+            .or_else(|| self.levels.last())
+            .copied()
+            .unwrap_or(0.)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn guideline_starts_at_a_row_a_second_and_speeds_up() {
+        let curve = GravityCurve::guideline();
+        assert!((curve.gravity(1) - 1. / 60.).abs() < 1e-6);
+        assert!(curve.levels.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert!(curve.gravity(20) <= MAX_GRAVITY);
+    }
+
+    #[test]
+    fn nes_counts_frames_per_row() {
+        let curve = GravityCurve::nes();
+        assert_eq!(curve.gravity(1), 1. / 48.);
+        assert_eq!(curve.gravity(30), 1.);
+    }
+
+    #[test]
+    fn last_level_carries_on_forever() {
+        let curve = GravityCurve::new(vec![0.5, 1.]);
+        assert_eq!(curve.gravity(0), 0.5);
+        assert_eq!(curve.gravity(2), 1.);
+        assert_eq!(curve.gravity(99), 1.);
+    }
+
+    #[test]
+    fn parses_presets_and_lists() {
+        assert_eq!(GravityCurve::parse("nes"), Ok(GravityCurve::nes()));
+        assert_eq!(
+            GravityCurve::parse("0.1, 2,50"),
+            Ok(GravityCurve::new(vec![0.1, 2., MAX_GRAVITY]))
+        );
+        assert_eq!(
+            GravityCurve::parse("0.1, 2,50").unwrap().gravity(3),
+            MAX_GRAVITY
+        );
+        assert!(GravityCurve::parse("fast").is_err());
+    }
+}
@@ -17,7 +17,7 @@ impl InputState {
     pub fn update(&mut self) {
         if self.pressed_this_frame {
             self.pressed_this_frame = false;
-            self.pressed_frames += 1;
+            self.pressed_frames = self.pressed_frames.saturating_add(1);
         } else {
             self.pressed_frames = 0;
         }
@@ -27,22 +27,145 @@ impl InputState {
         self.pressed_this_frame
     }
 
+    /// whether the input is down at all, including the first frame
+    pub fn down(self) -> bool {
+        self.pressed_frames > 0
+    }
+
     pub fn held(self) -> bool {
         self.pressed_frames > 1
     }
 
-    pub fn repeated(self, delay: u16, interval: u16) -> bool {
+    /// whether this is the first frame the input is down
+    pub fn just_pressed(self) -> bool {
         self.pressed_frames == 1
-            || (self.pressed_frames > delay && self.pressed_frames % interval == 0)
+    }
+}
+
+/// Which way held directions shift the current block. The most
+/// recently pressed direction wins, and switching directions
+/// keeps any DAS charge
+#[derive(Copy, Clone, Default)]
+pub struct AutoShift {
+    /// -1 for left, 1 for right, or 0 while neither is held
+    pub direction: i8,
+    /// frames since a direction was first held, which keeps
+    /// counting when switching directions
+    held_frames: u16,
+    /// whether the direction changed this frame
+    switched: bool,
+}
+
+impl AutoShift {
+    pub fn update(&mut self, left: InputState, right: InputState) {
+        let held = |direction| match direction {
+            -1 => left.down(),
+            1 => right.down(),
+            _ => false,
+        };
+
+        let direction = if left.just_pressed() && !right.just_pressed() {
+            -1
+        } else if right.just_pressed() && !left.just_pressed() {
+            1
+        } else if held(self.direction) {
+            self.direction
+        } else if left.down() {
+            -1
+        } else if right.down() {
+            1
+        } else {
+            0
+        };
+
+        self.switched = direction != self.direction;
+        self.direction = direction;
+        self.held_frames = if direction == 0 {
+            0
+        } else {
+            self.held_frames.saturating_add(1)
+        };
+    }
+
+    pub fn switched(self) -> bool {
+        self.switched
+    }
+
+    /// whether a direction has been held for at least `das` frames
+    pub fn charged(self, das: f32) -> bool {
+        self.direction != 0 && (self.held_frames - 1) as f32 >= das
+    }
+
+    /// how many times to shift this frame: once when the direction
+    /// changes, then every `arr` frames once it's been held for `das`
+    /// frames. Shifts as many times as it can once `arr` is 0
+    pub fn shifts(self, das: f32, arr: f32) -> usize {
+        if self.direction == 0 {
+            return 0;
+        }
+        if self.charged(das) && arr == 0. {
+            return usize::MAX;
+        }
+
+        // how many times it has auto repeated `frames` after being pressed
+        let auto_repeats = |frames: f32| {
+            if frames < das {
+                0.
+            } else {
+                ((frames - das) / arr).floor() + 1.
+            }
+        };
+
+        let frames = (self.held_frames - 1) as f32;
+        let repeats = (auto_repeats(frames) - auto_repeats(frames - 1.)) as usize;
+        if self.switched {
+            repeats.max(1)
+        } else {
+            repeats
+        }
     }
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash)]
 pub enum InputAction {
     Spin,
+    RotateCcw,
+    Rotate180,
     SoftDrop,
     HardDrop,
     MoveLeft,
     MoveRight,
     Cache,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hold(input: &mut InputState, frames: usize) {
+        for _ in 0..frames {
+            input.set_pressed(true);
+            input.update();
+        }
+    }
+
+    #[test]
+    fn presses_only_count_once() {
+        let mut input = InputState::default();
+        hold(&mut input, 1);
+        assert!(input.just_pressed());
+        hold(&mut input, 1);
+        assert!(input.down() && input.held() && !input.just_pressed());
+
+        input.update();
+        assert!(!input.down());
+    }
+
+    #[test]
+    fn holding_for_a_long_time_stays_held() {
+        let mut input = InputState::default();
+        hold(&mut input, usize::from(u16::MAX) + 10);
+        assert!(input.held());
+        assert!(!input.just_pressed());
+    }
+}
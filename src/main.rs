@@ -7,14 +7,35 @@ use main_state::MainState;
 
 mod block;
 
+mod config;
+
+mod engine;
+
+mod garbage;
+
+mod gravity;
+
 mod input;
 
+mod mode;
+
+mod piece_queue;
+
+mod randomizer;
+
+mod scoring;
+
 mod actions;
 
 mod consts;
 use consts::*;
 
 fn main() -> GameResult {
+    let config = config::Config::from_args(std::env::args().skip(1)).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+
     let (ctx, event_loop) = &mut ggez::ContextBuilder::new("Tetrs", "Fish")
         .window_setup(ggez::conf::WindowSetup::default().title("Tetrs"))
         .window_mode(
@@ -29,6 +50,7 @@ fn main() -> GameResult {
     let main_state = &mut MainState {
         current_state: Box::new(menu_state::MenuState::new(font, None)),
         font,
+        config,
     };
 
     event::run(ctx, event_loop, main_state)
@@ -1,11 +1,19 @@
 use ggez::{event::EventHandler, Context, GameResult};
 
+use crate::config::Config;
 use crate::game_state;
 use crate::menu_state::{self, GameOverData};
+use crate::mode::ModeKind;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub enum Signal {
-    StartGame,
+    /// go to the mode select screen
+    SelectMode,
+    /// go to the screen for picking marathon's start level
+    SelectMarathonLevel {
+        endless: bool,
+    },
+    StartGame(ModeKind),
     EndGame(GameOverData),
 }
 
@@ -18,6 +26,7 @@ pub trait StateTrait: SignalState + EventHandler {}
 pub struct MainState {
     pub current_state: Box<dyn StateTrait>,
     pub font: ggez::graphics::Font,
+    pub config: Config,
 }
 
 impl EventHandler for MainState {
@@ -52,8 +61,19 @@ impl EventHandler for MainState {
 impl MainState {
     fn process_signal(&mut self, signal: Signal) {
         match signal {
-            Signal::StartGame => {
-                self.current_state = Box::new(game_state::GameState::new(self.font));
+            Signal::SelectMode => {
+                self.current_state = Box::new(menu_state::MenuState::mode_select(self.font));
+            }
+            Signal::SelectMarathonLevel { endless } => {
+                self.current_state =
+                    Box::new(menu_state::MenuState::marathon_select(self.font, endless));
+            }
+            Signal::StartGame(mode) => {
+                self.current_state = Box::new(game_state::GameState::new(
+                    self.font,
+                    self.config.clone(),
+                    mode,
+                ));
             }
             Signal::EndGame(game_data) => {
                 self.current_state =
@@ -1,11 +1,13 @@
 use ggez::{
     event::EventHandler,
     graphics::{self, Color, DrawMode, DrawParam, Font, Rect, Scale, Text, TextFragment},
-    Context, GameResult,
+    timer, Context, GameResult,
 };
 
+use crate::consts::{FRAMES_PER_SECOND, MARATHON_LEVELS};
+use crate::engine::TopOut;
 use crate::main_state::{Signal, SignalState, StateTrait};
-use std::time::Duration;
+use crate::mode::ModeKind;
 
 pub struct Button {
     rect: Rect,
@@ -17,6 +19,7 @@ pub struct Button {
 }
 
 impl Button {
+    #[allow(clippy::too_many_arguments)]
     fn new(
         text: &str,
         font: Font,
@@ -43,10 +46,22 @@ impl Button {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+/// How a game ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// the mode's goal was reached
+    Cleared,
+    ToppedOut(TopOut),
+}
+
+#[derive(Clone, Debug)]
 pub struct GameOverData {
-    pub lines: usize,
-    pub time: Duration,
+    pub mode: ModeKind,
+    pub outcome: Outcome,
+    /// the mode's summary of the game
+    pub results: Vec<String>,
+    /// seed the game was played with, so that it can be replayed
+    pub seed: u64,
 }
 
 pub struct MenuState {
@@ -72,18 +87,25 @@ impl MenuState {
             250.0,
             275.0,
             100.0,
-            Signal::StartGame,
+            Signal::SelectMode,
         );
 
         let game_over_text = game_over_data.map(|data| {
+            let heading = match data.outcome {
+                Outcome::ToppedOut(reason) => format!("Topped Out! \n {}", reason.description()),
+                Outcome::Cleared => format!("{} Cleared!", data.mode.name()),
+            };
+            // long results are made smaller to fit under the button
+            let scale = if data.results.len() > 5 { 16.0 } else { 24.0 };
             Text::new(
                 TextFragment::new(format!(
-                    "You Lose! \n Lines: {} \n Time: {}s",
-                    data.lines,
-                    data.time.as_secs()
+                    "{} \n {} \n Seed: {}",
+                    heading,
+                    data.results.join(" \n "),
+                    data.seed
                 ))
                 .font(text_font)
-                .scale(Scale::uniform(49.0)),
+                .scale(Scale::uniform(scale)),
             )
         });
 
@@ -96,6 +118,93 @@ impl MenuState {
     }
 }
 
+impl MenuState {
+    /// a screen with a button to start each mode
+    pub fn mode_select(text_font: Font) -> Self {
+        let header_text = Text::new(
+            TextFragment::new("TETRS")
+                .scale(Scale::uniform(120.0))
+                .font(text_font),
+        );
+        let buttons = ModeKind::ALL
+            .iter()
+            .enumerate()
+            .map(|(i, &mode)| {
+                Button::new(
+                    mode.name(),
+                    text_font,
+                    Color::new(1.0, 0.0, 0.0, 1.0),
+                    Color::new(0.8, 0.0, 0.0, 1.0),
+                    117.5,
+                    200.0 + 75.0 * i as f32,
+                    275.0,
+                    60.0,
+                    match mode {
+                        ModeKind::Marathon { .. } => Signal::SelectMarathonLevel { endless: false },
+                        _ => Signal::StartGame(mode),
+                    },
+                )
+            })
+            .collect();
+
+        MenuState {
+            header_text,
+            buttons,
+            game_over_text: None,
+            sent_signals: Vec::new(),
+        }
+    }
+}
+
+impl MenuState {
+    /// a screen with a button to start marathon from each level,
+    /// and one to switch between the normal and endless variants
+    pub fn marathon_select(text_font: Font, endless: bool) -> Self {
+        let header_text = Text::new(
+            TextFragment::new("TETRS")
+                .scale(Scale::uniform(120.0))
+                .font(text_font),
+        );
+        let mut buttons = (1..=MARATHON_LEVELS)
+            .map(|level| {
+                let (column, row) = ((level - 1) % 5, (level - 1) / 5);
+                Button::new(
+                    &level.to_string(),
+                    text_font,
+                    Color::new(1.0, 0.0, 0.0, 1.0),
+                    Color::new(0.8, 0.0, 0.0, 1.0),
+                    50.0 + 85.0 * column as f32,
+                    200.0 + 65.0 * row as f32,
+                    70.0,
+                    50.0,
+                    Signal::StartGame(ModeKind::Marathon {
+                        start_level: level,
+                        endless,
+                    }),
+                )
+            })
+            .collect::<Vec<Button>>();
+        buttons.push(Button::new(
+            if endless { "ENDLESS" } else { "150 LINES" },
+            text_font,
+            Color::new(0.5, 0.5, 0.5, 1.0),
+            Color::new(0.4, 0.4, 0.4, 1.0),
+            117.5,
+            420.0,
+            275.0,
+            50.0,
+            Signal::SelectMarathonLevel { endless: !endless },
+        ));
+
+        MenuState {
+            header_text,
+            buttons,
+            game_over_text: None,
+            sent_signals: Vec::new(),
+        }
+    }
+}
+
 impl EventHandler for MenuState {
     fn update(&mut self, ctx: &mut Context) -> GameResult {
         let mouse_rect = {
@@ -107,6 +216,10 @@ impl EventHandler for MenuState {
             btn.hovered = btn.rect.overlaps(&mouse_rect);
         });
 
+        // use up the fixed timestep while in the menu, so that
+        // a new game doesn't start by catching up on it
+        while timer::check_update_time(ctx, FRAMES_PER_SECOND) {}
+
         Ok(())
     }
 
@@ -116,11 +229,11 @@ impl EventHandler for MenuState {
         graphics::draw(ctx, &self.header_text, DrawParam::new().dest([62.5, 50.0]))?;
 
         self.buttons.iter().for_each(|btn| {
-            draw_button(&btn, ctx).unwrap();
+            draw_button(btn, ctx).unwrap();
         });
 
         if let Some(text) = &self.game_over_text {
-            graphics::draw(ctx, text, DrawParam::new().dest([150.0, 400.0]))?;
+            graphics::draw(ctx, text, DrawParam::new().dest([150.0, 370.0]))?;
         }
 
         graphics::present(ctx).expect("error rendering");
@@ -142,7 +255,7 @@ impl EventHandler for MenuState {
                 .iter()
                 .filter_map(|btn| {
                     if btn.rect.overlaps(&mouse_rect) {
-                        Some(btn.signal)
+                        Some(btn.signal.clone())
                     } else {
                         None
                     }
@@ -169,12 +282,14 @@ fn draw_button(button: &Button, ctx: &mut Context) -> GameResult {
 
     let rect = graphics::Mesh::new_rectangle(ctx, DrawMode::fill(), button.rect, color).unwrap();
     graphics::draw(ctx, &rect, DrawParam::new()).unwrap();
+    // the text is centered on the button
+    let (width, height) = button.text.dimensions(ctx);
     graphics::draw(
         ctx,
         &button.text,
         DrawParam::new().dest([
-            button.rect.x + (button.rect.w * 0.1825),
-            button.rect.y + (button.rect.h * 0.125),
+            button.rect.x + (button.rect.w - width as f32) / 2.,
+            button.rect.y + (button.rect.h - height as f32) / 2.,
         ]),
     )
     .unwrap();
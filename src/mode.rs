@@ -0,0 +1,589 @@
+use crate::config::Config;
+use crate::consts::*;
+use crate::engine::{Engine, GameEvent};
+use crate::gravity::GravityCurve;
+use crate::randomizer::RandomizerKind;
+
+use std::time::Duration;
+
+/// The modes that can be picked from the mode select screen
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ModeKind {
+    /// no goal, play until topping out
+    Endless,
+    /// clear 40 lines as fast as possible
+    Sprint,
+    /// score as much as possible in 2 minutes
+    Ultra,
+    /// clear 150 lines from the given level, or as many as
+    /// possible if it's endless
+    Marathon { start_level: usize, endless: bool },
+    /// clear all of the garbage as fast as possible
+    Dig,
+}
+
+impl ModeKind {
+    /// every mode, in the order they're shown in
+    pub const ALL: [ModeKind; 5] = [
+        ModeKind::Endless,
+        ModeKind::Sprint,
+        ModeKind::Ultra,
+        ModeKind::Marathon {
+            start_level: 1,
+            endless: false,
+        },
+        ModeKind::Dig,
+    ];
+
+    pub fn build(self) -> Box<dyn GameMode> {
+        match self {
+            ModeKind::Endless => Box::new(Endless),
+            ModeKind::Sprint => Box::new(Sprint),
+            ModeKind::Ultra => Box::new(Ultra::default()),
+            ModeKind::Marathon {
+                start_level,
+                endless,
+            } => Box::new(Marathon {
+                start_level: start_level.clamp(1, MARATHON_LEVELS),
+                endless,
+            }),
+            ModeKind::Dig => Box::new(Dig),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ModeKind::Endless => "ENDLESS",
+            ModeKind::Sprint => "SPRINT",
+            ModeKind::Ultra => "ULTRA",
+            ModeKind::Marathon { .. } => "MARATHON",
+            ModeKind::Dig => "DIG",
+        }
+    }
+}
+
+/// The goal, settings and displays that make up a way of playing.
+/// Modes only look at the engine, so they work the same headless
+pub trait GameMode {
+    /// changes the settings a game is played with, like
+    /// its gravity curve or randomizer
+    fn configure(&self, _config: &mut Config) {}
+
+    /// keeps track of anything the mode needs from a step of the engine
+    fn handle_events(&mut self, _events: &[GameEvent]) {}
+
+    /// whether the goal has been reached, which ends the game
+    fn finished(&self, _engine: &Engine) -> bool {
+        false
+    }
+
+    /// whether the game is about to end, which turns the info panel red
+    fn warning(&self, _engine: &Engine) -> bool {
+        false
+    }
+
+    /// lines for the info panel
+    fn hud(&self, engine: &Engine) -> Vec<String> {
+        vec![
+            format!("Score: {}", engine.score.points),
+            format!("Lines: {}", engine.lines),
+            format!("Level: {}", engine.level),
+            duration_display(engine.time()),
+        ]
+    }
+
+    /// lines summing up a game once it's over
+    fn results(&self, engine: &Engine) -> Vec<String> {
+        vec![
+            format!("Score: {}", engine.score.points),
+            format!("Lines: {}", engine.lines),
+            format!("Time: {}", duration_display(engine.time())),
+        ]
+    }
+}
+
+/// Plays on until topping out, with all of the settings
+/// left as they were given
+pub struct Endless;
+
+impl GameMode for Endless {}
+
+/// Clears `SPRINT_LINES` lines against the clock, at
+/// level 1 gravity the whole way through. The clock is the
+/// engine's frame count, so times are exact to the frame
+/// rather than the millisecond they're shown to
+pub struct Sprint;
+
+impl GameMode for Sprint {
+    fn configure(&self, config: &mut Config) {
+        config.start_level = 1;
+        config.gravity = GravityCurve::new(vec![GravityCurve::guideline().gravity(1)]);
+        config.randomizer = RandomizerKind::SevenBag;
+    }
+
+    fn finished(&self, engine: &Engine) -> bool {
+        engine.lines >= SPRINT_LINES
+    }
+
+    fn hud(&self, engine: &Engine) -> Vec<String> {
+        vec![
+            format!("Lines left: {}", SPRINT_LINES.saturating_sub(engine.lines)),
+            format!("Pieces: {}", engine.pieces),
+            format!("PPS: {:.2}", pieces_per_second(engine)),
+            precise_duration_display(engine.time()),
+            format!("Frames: {}", engine.frames),
+        ]
+    }
+
+    fn results(&self, engine: &Engine) -> Vec<String> {
+        vec![
+            format!("Time: {}", precise_duration_display(engine.time())),
+            format!(
+                "Exact to the frame: {} at {} fps",
+                engine.frames, FRAMES_PER_SECOND
+            ),
+            format!("PPS: {:.2}", pieces_per_second(engine)),
+            format!("KPP: {:.2}", keys_per_piece(engine)),
+        ]
+    }
+}
+
+/// Scores as much as possible before `ULTRA_SECS` run out,
+/// keeping count of the kinds of clears made along the way
+/// and how much garbage they would have sent
+#[derive(Default)]
+pub struct Ultra {
+    /// singles, doubles, triples and tetrises that weren't T-spins
+    pub clears: [usize; 4],
+    pub tspins: usize,
+    pub perfect_clears: usize,
+    /// attack from each kind of clear, in the same order as `clears`
+    /// and then T-spins. Combo, back-to-back and perfect clear bonuses
+    /// count towards the clear that earned them
+    pub attack: [usize; 5],
+}
+
+impl Ultra {
+    fn time_left(engine: &Engine) -> Duration {
+        Duration::from_secs(ULTRA_SECS).saturating_sub(engine.time())
+    }
+}
+
+impl GameMode for Ultra {
+    fn configure(&self, config: &mut Config) {
+        config.start_level = 1;
+        config.randomizer = RandomizerKind::SevenBag;
+    }
+
+    fn handle_events(&mut self, events: &[GameEvent]) {
+        let tspin = events
+            .iter()
+            .any(|event| matches!(event, GameEvent::TSpin { lines, .. } if *lines > 0));
+        let mut kind = None;
+        let mut attack = 0;
+        for event in events {
+            match event {
+                GameEvent::TSpin { lines, .. } if *lines > 0 => {
+                    self.tspins += 1;
+                    kind = Some(4);
+                }
+                GameEvent::LinesCleared(lines) if !tspin => {
+                    let index = (lines - 1).min(3);
+                    self.clears[index] += 1;
+                    kind = Some(index);
+                }
+                GameEvent::PerfectClear => self.perfect_clears += 1,
+                GameEvent::Attack(lines) => attack += lines,
+                _ => {}
+            }
+        }
+
+        if let Some(kind) = kind {
+            self.attack[kind] += attack;
+        }
+    }
+
+    fn finished(&self, engine: &Engine) -> bool {
+        Ultra::time_left(engine) == Duration::from_secs(0)
+    }
+
+    fn warning(&self, engine: &Engine) -> bool {
+        Ultra::time_left(engine) <= Duration::from_secs(ULTRA_WARNING_SECS)
+    }
+
+    fn hud(&self, engine: &Engine) -> Vec<String> {
+        vec![
+            format!("Score: {}", engine.score.points),
+            format!("Lines: {}", engine.lines),
+            format!("Level: {}", engine.level),
+            precise_duration_display(Ultra::time_left(engine)),
+        ]
+    }
+
+    fn results(&self, engine: &Engine) -> Vec<String> {
+        let names = ["Singles", "Doubles", "Triples", "Tetrises", "T-Spins"];
+        let counts = self.clears.iter().chain(std::iter::once(&self.tspins));
+
+        let mut results = vec![
+            format!("Score: {}  Lines: {}", engine.score.points, engine.lines),
+            format!("Attack: {}", self.attack.iter().sum::<usize>()),
+        ];
+        results.extend(
+            names
+                .iter()
+                .zip(counts)
+                .zip(self.attack.iter())
+                .map(|((name, count), attack)| format!("{}: {}, {} attack", name, count, attack)),
+        );
+        results.push(format!("PCs: {}", self.perfect_clears));
+        results
+    }
+}
+
+/// Clears `MARATHON_LINES` lines, going up a level every 10 lines up
+/// to `MARATHON_LEVELS`, with gravity from the guideline. The endless
+/// variant has no goal
+pub struct Marathon {
+    pub start_level: usize,
+    pub endless: bool,
+}
+
+impl GameMode for Marathon {
+    fn configure(&self, config: &mut Config) {
+        config.start_level = self.start_level;
+        config.lines_per_level = LINES_PER_LEVEL;
+        config.max_level = Some(MARATHON_LEVELS);
+        config.gravity = GravityCurve::guideline();
+        config.randomizer = RandomizerKind::SevenBag;
+    }
+
+    fn finished(&self, engine: &Engine) -> bool {
+        !self.endless && engine.lines >= MARATHON_LINES
+    }
+
+    fn hud(&self, engine: &Engine) -> Vec<String> {
+        let lines = if self.endless {
+            format!("Lines: {}", engine.lines)
+        } else {
+            format!("Lines: {}/{}", engine.lines, MARATHON_LINES)
+        };
+        vec![
+            format!("Score: {}", engine.score.points),
+            lines,
+            format!("Level: {}", engine.level),
+            duration_display(engine.time()),
+        ]
+    }
+
+    fn results(&self, engine: &Engine) -> Vec<String> {
+        vec![
+            format!("Score: {}", engine.score.points),
+            format!("Lines: {}", engine.lines),
+            format!("Level: {}", engine.level),
+            format!("Time: {}", duration_display(engine.time())),
+        ]
+    }
+}
+
+/// Clears all of the garbage the board starts with against the clock.
+/// Starts with `DIG_ROWS` rows of garbage with holes that move every
+/// row, unless other amounts are given in the settings
+pub struct Dig;
+
+impl GameMode for Dig {
+    fn configure(&self, config: &mut Config) {
+        config.start_level = 1;
+        config.gravity = GravityCurve::new(vec![GravityCurve::guideline().gravity(1)]);
+        config.randomizer = RandomizerKind::SevenBag;
+        // no garbage at all would leave nothing to dig, so it
+        // gets the default amount like when none is given
+        if config.garbage_rows.unwrap_or(0) == 0 {
+            config.garbage_rows = Some(DIG_ROWS.min(config.board.height as usize));
+        }
+        config.garbage.messiness.get_or_insert(DIG_MESSINESS);
+    }
+
+    fn finished(&self, engine: &Engine) -> bool {
+        engine.garbage_left == 0
+    }
+
+    fn hud(&self, engine: &Engine) -> Vec<String> {
+        vec![
+            format!("Garbage left: {}", engine.garbage_left),
+            format!("Pieces: {}", engine.pieces),
+            format!("PPS: {:.2}", pieces_per_second(engine)),
+            precise_duration_display(engine.time()),
+        ]
+    }
+
+    fn results(&self, engine: &Engine) -> Vec<String> {
+        vec![
+            format!("Time: {}", precise_duration_display(engine.time())),
+            format!("Pieces: {}", engine.pieces),
+            format!("PPS: {:.2}", pieces_per_second(engine)),
+        ]
+    }
+}
+
+fn pieces_per_second(engine: &Engine) -> f32 {
+    let secs = engine.time().as_secs_f32();
+    if secs > 0. {
+        engine.pieces as f32 / secs
+    } else {
+        0.
+    }
+}
+
+fn keys_per_piece(engine: &Engine) -> f32 {
+    if engine.pieces > 0 {
+        engine.key_presses as f32 / engine.pieces as f32
+    } else {
+        0.
+    }
+}
+
+/// formats a duration as minutes and seconds, e.g. 1:05
+pub fn duration_display(duration: Duration) -> String {
+    let (mins, secs) = (duration.as_secs() / 60, duration.as_secs() % 60);
+    if secs < 10 {
+        format!("{}:0{}", mins, secs)
+    } else {
+        format!("{}:{}", mins, secs)
+    }
+}
+
+/// formats a duration down to the millisecond, e.g. 1:05.250
+pub fn precise_duration_display(duration: Duration) -> String {
+    format!(
+        "{}.{:03}",
+        duration_display(duration),
+        duration.subsec_millis()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::{Block, BlockType, Orientation};
+    use crate::input::InputAction;
+    use crate::scoring::TSpin;
+
+    /// sets up a mode and an engine the way `GameState` does
+    fn start(kind: ModeKind, mut config: Config) -> (Box<dyn GameMode>, Engine) {
+        let mode = kind.build();
+        config.seed = Some(1);
+        mode.configure(&mut config);
+        (mode, Engine::new(config))
+    }
+
+    #[test]
+    fn every_mode_has_its_own_name() {
+        for (i, kind) in ModeKind::ALL.iter().enumerate() {
+            for other in ModeKind::ALL[i + 1..].iter() {
+                assert_ne!(kind.name(), other.name());
+            }
+        }
+    }
+
+    #[test]
+    fn endless_keeps_the_settings_and_never_finishes() {
+        let config = Config {
+            start_level: 7,
+            randomizer: RandomizerKind::Tgm,
+            ..Config::default()
+        };
+        let (mode, mut engine) = start(ModeKind::Endless, config);
+        assert_eq!(engine.config.start_level, 7);
+        assert_eq!(engine.config.randomizer, RandomizerKind::Tgm);
+
+        engine.lines = 1000;
+        engine.frames = 1_000_000;
+        assert!(!mode.finished(&engine));
+        assert!(!mode.warning(&engine));
+        assert!(!mode.hud(&engine).is_empty());
+        assert!(!mode.results(&engine).is_empty());
+    }
+
+    #[test]
+    fn durations_pad_their_seconds() {
+        assert_eq!(duration_display(Duration::from_secs(65)), "1:05");
+        assert_eq!(duration_display(Duration::from_secs(600)), "10:00");
+        assert_eq!(
+            precise_duration_display(Duration::from_millis(65_250)),
+            "1:05.250"
+        );
+    }
+
+    #[test]
+    fn sprint_ends_at_forty_lines() {
+        let config = Config {
+            start_level: 5,
+            randomizer: RandomizerKind::Tgm,
+            ..Config::default()
+        };
+        let (mode, mut engine) = start(ModeKind::Sprint, config);
+        assert_eq!(engine.level, 1);
+        assert_eq!(engine.config.randomizer, RandomizerKind::SevenBag);
+        assert_eq!(
+            engine.config.gravity.gravity(15),
+            engine.config.gravity.gravity(1)
+        );
+
+        engine.lines = SPRINT_LINES - 1;
+        assert!(!mode.finished(&engine));
+        engine.lines = SPRINT_LINES;
+        assert!(mode.finished(&engine));
+        assert!(mode.hud(&engine).contains(&"Lines left: 0".to_string()));
+    }
+
+    #[test]
+    fn sprint_results_are_timed_to_the_frame() {
+        let (mode, mut engine) = start(ModeKind::Sprint, Config::default());
+        for _ in 0..100 {
+            engine.step(&[]);
+        }
+        engine.step(&[InputAction::HardDrop]);
+        engine.step(&[]);
+        engine.step(&[InputAction::HardDrop]);
+
+        let results = mode.results(&engine);
+        assert_eq!(results[0], "Time: 0:01.716");
+        assert_eq!(results[1], "Exact to the frame: 103 at 60 fps");
+        assert_eq!(results[2], format!("PPS: {:.2}", 2. / 1.716_666));
+        assert_eq!(results[3], "KPP: 1.00");
+    }
+
+    #[test]
+    fn ultra_ends_after_exactly_two_minutes() {
+        let (mode, mut engine) = start(ModeKind::Ultra, Config::default());
+        let frames = ULTRA_SECS as usize * FRAMES_PER_SECOND as usize;
+
+        engine.frames = frames - 1;
+        assert!(!mode.finished(&engine));
+        assert!(mode.warning(&engine));
+        assert!(mode.hud(&engine).contains(&"0:00.016".to_string()));
+        engine.frames = frames;
+        assert!(mode.finished(&engine));
+
+        engine.frames = frames - ULTRA_WARNING_SECS as usize * FRAMES_PER_SECOND as usize - 1;
+        assert!(!mode.warning(&engine));
+    }
+
+    #[test]
+    fn ultra_breaks_down_clears_and_attack() {
+        let (mut mode, engine) = start(ModeKind::Ultra, Config::default());
+        let steps = [
+            vec![GameEvent::LinesCleared(4), GameEvent::Attack(4)],
+            vec![GameEvent::LinesCleared(4), GameEvent::Attack(5)],
+            vec![GameEvent::LinesCleared(1)],
+            vec![
+                GameEvent::TSpin {
+                    kind: TSpin::Full,
+                    lines: 2,
+                },
+                GameEvent::Attack(5),
+                GameEvent::LinesCleared(2),
+                GameEvent::PerfectClear,
+            ],
+        ];
+        for events in steps.iter() {
+            mode.handle_events(events);
+        }
+
+        let results = mode.results(&engine);
+        assert_eq!(results[1], "Attack: 14");
+        assert_eq!(results[2], "Singles: 1, 0 attack");
+        assert_eq!(results[3], "Doubles: 0, 0 attack");
+        assert_eq!(results[5], "Tetrises: 2, 9 attack");
+        assert_eq!(results[6], "T-Spins: 1, 5 attack");
+        assert_eq!(results[7], "PCs: 1");
+    }
+
+    fn marathon(start_level: usize, endless: bool) -> ModeKind {
+        ModeKind::Marathon {
+            start_level,
+            endless,
+        }
+    }
+
+    #[test]
+    fn marathon_starts_from_levels_one_to_fifteen() {
+        let (_, engine) = start(marathon(20, false), Config::default());
+        assert_eq!(engine.level, MARATHON_LEVELS);
+        let (_, engine) = start(marathon(0, false), Config::default());
+        assert_eq!(engine.level, 1);
+        assert_eq!(engine.config.gravity, GravityCurve::guideline());
+        assert_eq!(engine.config.lines_per_level, LINES_PER_LEVEL);
+    }
+
+    #[test]
+    fn marathon_ends_at_150_lines_unless_endless() {
+        let (mode, mut engine) = start(marathon(1, false), Config::default());
+        engine.lines = MARATHON_LINES - 1;
+        assert!(!mode.finished(&engine));
+        engine.lines = MARATHON_LINES;
+        assert!(mode.finished(&engine));
+        assert!(mode.hud(&engine).contains(&"Lines: 150/150".to_string()));
+
+        let (mode, mut engine) = start(marathon(1, true), Config::default());
+        engine.lines = 1000;
+        assert!(!mode.finished(&engine));
+    }
+
+    #[test]
+    fn marathon_stops_levelling_up_at_fifteen() {
+        let mut config = Config::default();
+        config.board.width = 4;
+        let (_, mut engine) = start(marathon(14, false), config);
+
+        let mut events = Vec::new();
+        for _ in 0..25 {
+            engine.spawn(BlockType::Line);
+            events.extend(engine.step(&[InputAction::HardDrop]));
+            engine.step(&[]);
+        }
+        assert_eq!(engine.lines, 25);
+        assert_eq!(engine.level, MARATHON_LEVELS);
+        let level_ups: Vec<&GameEvent> = events
+            .iter()
+            .filter(|event| matches!(event, GameEvent::LevelUp(_)))
+            .collect();
+        assert_eq!(level_ups, vec![&GameEvent::LevelUp(15)]);
+    }
+
+    fn dig(args: &[&str]) -> (Box<dyn GameMode>, Engine) {
+        let config = Config::from_args(args.iter().map(|arg| arg.to_string())).unwrap();
+        start(ModeKind::Dig, config)
+    }
+
+    #[test]
+    fn dig_starts_with_messy_garbage() {
+        let (mode, engine) = dig(&[]);
+        assert_eq!(engine.garbage_left, DIG_ROWS);
+        assert_eq!(engine.config.garbage.messiness, Some(DIG_MESSINESS));
+        assert!(!mode.finished(&engine));
+
+        let (_, engine) = dig(&["--garbage", "3", "--messiness", "0"]);
+        assert_eq!(engine.garbage_left, 3);
+        assert_eq!(engine.config.garbage.messiness, Some(0.));
+
+        let (_, engine) = dig(&["--height", "6"]);
+        assert_eq!(engine.garbage_left, 6);
+    }
+
+    #[test]
+    fn dig_always_has_garbage_to_dig() {
+        let (mode, engine) = dig(&["--garbage", "0"]);
+        assert_eq!(engine.garbage_left, DIG_ROWS);
+        assert!(!mode.finished(&engine));
+    }
+
+    #[test]
+    fn dig_ends_once_the_garbage_is_cleared() {
+        let (mode, mut engine) = dig(&["--width", "4", "--garbage", "1", "--garbage-hole", "1"]);
+        engine.current_block = Block::new(BlockType::Line, Orientation::Left).translate(-1, 0);
+        engine.step(&[InputAction::HardDrop]);
+        assert_eq!(engine.garbage_left, 0);
+        assert!(mode.finished(&engine));
+    }
+}
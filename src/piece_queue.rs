@@ -0,0 +1,82 @@
+use crate::block::BlockType;
+use crate::randomizer::Randomizer;
+
+use std::collections::VecDeque;
+
+use rand::RngCore;
+
+/// The upcoming blocks, dealt by a randomizer and kept
+/// far enough ahead that previews never run dry, even
+/// across the end of a bag
+#[derive(Clone)]
+pub struct PieceQueue {
+    pub randomizer: Box<dyn Randomizer>,
+    upcoming: VecDeque<BlockType>,
+    /// how many blocks can always be peeked at
+    pub lookahead: usize,
+}
+
+impl PieceQueue {
+    pub fn new(randomizer: Box<dyn Randomizer>, lookahead: usize, rng: &mut dyn RngCore) -> Self {
+        let mut queue = PieceQueue {
+            randomizer,
+            upcoming: VecDeque::with_capacity(lookahead + 1),
+            lookahead,
+        };
+        queue.fill(rng);
+        queue
+    }
+
+    /// takes the next block, dealing another one to replace it
+    pub fn next(&mut self, rng: &mut dyn RngCore) -> BlockType {
+        self.fill(rng);
+        let blocktype = self.upcoming.pop_front().unwrap();
+        self.fill(rng);
+        blocktype
+    }
+
+    /// the next `n` blocks, up to `lookahead`, without taking them
+    pub fn peek(&self, n: usize) -> impl Iterator<Item = BlockType> + '_ {
+        self.upcoming.iter().take(n).copied()
+    }
+
+    fn fill(&mut self, rng: &mut dyn RngCore) {
+        while self.upcoming.len() < self.lookahead.max(1) {
+            self.upcoming.push_back(self.randomizer.next(rng));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consts::MAX_PREVIEWS;
+    use crate::randomizer::RandomizerKind;
+
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn peeks_across_bags() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut queue = PieceQueue::new(RandomizerKind::SevenBag.build(), MAX_PREVIEWS, &mut rng);
+
+        // every block until well into the third bag is seen before it's dealt
+        for _ in 0..16 {
+            let peeked: Vec<BlockType> = queue.peek(MAX_PREVIEWS).collect();
+            assert_eq!(peeked.len(), MAX_PREVIEWS);
+            assert_eq!(queue.next(&mut rng), peeked[0]);
+            assert!(queue.peek(MAX_PREVIEWS - 1).eq(peeked[1..].iter().copied()));
+        }
+    }
+
+    #[test]
+    fn peeks_no_further_than_the_lookahead() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let queue = PieceQueue::new(RandomizerKind::SevenBag.build(), 3, &mut rng);
+        assert_eq!(queue.peek(7).count(), 3);
+
+        let mut queue = PieceQueue::new(RandomizerKind::SevenBag.build(), 0, &mut rng);
+        assert_eq!(queue.peek(0).count(), 0);
+        queue.next(&mut rng);
+    }
+}
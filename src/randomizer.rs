@@ -0,0 +1,282 @@
+use crate::block::BlockType;
+use crate::consts::*;
+
+use std::collections::VecDeque;
+
+use rand::{seq::SliceRandom, Rng, RngCore};
+
+/// Decides the order blocks come in
+pub trait Randomizer {
+    /// the next block in the sequence
+    fn next(&mut self, rng: &mut dyn RngCore) -> BlockType;
+
+    fn box_clone(&self) -> Box<dyn Randomizer>;
+}
+
+impl Clone for Box<dyn Randomizer> {
+    fn clone(&self) -> Self {
+        self.box_clone()
+    }
+}
+
+/// The randomizers that can be picked for a game
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RandomizerKind {
+    /// every block once, shuffled
+    SevenBag,
+    /// every block twice, shuffled
+    FourteenBag,
+    /// any block, with no protection against floods or droughts
+    Random,
+    /// NES Tetris, which rerolls once if it picks the previous block
+    Nes,
+    /// TGM, which rerolls a few times if it picks one of the last four blocks
+    Tgm,
+}
+
+impl RandomizerKind {
+    pub fn build(self) -> Box<dyn Randomizer> {
+        match self {
+            RandomizerKind::SevenBag => Box::new(Bag::new(1)),
+            RandomizerKind::FourteenBag => Box::new(Bag::new(2)),
+            RandomizerKind::Random => Box::new(PureRandom),
+            RandomizerKind::Nes => Box::new(NesRandomizer::default()),
+            RandomizerKind::Tgm => Box::new(HistoryRandomizer::tgm()),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        match text {
+            "7bag" => Ok(RandomizerKind::SevenBag),
+            "14bag" => Ok(RandomizerKind::FourteenBag),
+            "random" => Ok(RandomizerKind::Random),
+            "nes" => Ok(RandomizerKind::Nes),
+            "tgm" => Ok(RandomizerKind::Tgm),
+            _ => Err(format!("unknown randomizer: {}", text)),
+        }
+    }
+}
+
+/// Deals out shuffled bags with `copies` of each block
+#[derive(Clone)]
+pub struct Bag {
+    pub copies: usize,
+    pub bag: Vec<BlockType>,
+}
+
+impl Bag {
+    pub fn new(copies: usize) -> Self {
+        Bag {
+            copies,
+            bag: Vec::with_capacity(TYPES.len() * copies),
+        }
+    }
+}
+
+impl Randomizer for Bag {
+    fn next(&mut self, rng: &mut dyn RngCore) -> BlockType {
+        if self.bag.is_empty() {
+            self.bag = TYPES
+                .iter()
+                .cycle()
+                .take(TYPES.len() * self.copies)
+                .copied()
+                .collect();
+            self.bag.shuffle(rng);
+        }
+
+        self.bag.pop().unwrap()
+    }
+
+    fn box_clone(&self) -> Box<dyn Randomizer> {
+        Box::new(self.clone())
+    }
+}
+
+#[derive(Clone)]
+pub struct PureRandom;
+
+impl Randomizer for PureRandom {
+    fn next(&mut self, rng: &mut dyn RngCore) -> BlockType {
+        TYPES[rng.gen_range(0, TYPES.len())]
+    }
+
+    fn box_clone(&self) -> Box<dyn Randomizer> {
+        Box::new(self.clone())
+    }
+}
+
+/// Rolls an 8 sided die where the 8th side means reroll, and
+/// also rerolls if it lands on the previous block. The reroll
+/// is a plain 7 sided die, so repeats are only less likely
+#[derive(Clone, Default)]
+pub struct NesRandomizer {
+    pub previous: Option<BlockType>,
+}
+
+impl Randomizer for NesRandomizer {
+    fn next(&mut self, rng: &mut dyn RngCore) -> BlockType {
+        let roll = rng.gen_range(0, TYPES.len() + 1);
+        let blocktype = match TYPES.get(roll) {
+            Some(&blocktype) if Some(blocktype) != self.previous => blocktype,
+            _ => TYPES[rng.gen_range(0, TYPES.len())],
+        };
+
+        self.previous = Some(blocktype);
+        blocktype
+    }
+
+    fn box_clone(&self) -> Box<dyn Randomizer> {
+        Box::new(self.clone())
+    }
+}
+
+/// Remembers the last few blocks and rerolls up to `rolls`
+/// times while it picks one of them
+#[derive(Clone)]
+pub struct HistoryRandomizer {
+    pub history: VecDeque<BlockType>,
+    pub rolls: usize,
+    /// whether the first block has been dealt, since it
+    /// is never a block that can't be placed flat
+    pub started: bool,
+}
+
+impl HistoryRandomizer {
+    /// the randomizer from TGM2, with a history of 4
+    /// blocks that starts out as Z, S, Z, S and 6 rolls
+    pub fn tgm() -> Self {
+        HistoryRandomizer {
+            history: [BlockType::Z, BlockType::S, BlockType::Z, BlockType::S]
+                .iter()
+                .copied()
+                .collect(),
+            rolls: 6,
+            started: false,
+        }
+    }
+}
+
+impl Randomizer for HistoryRandomizer {
+    fn next(&mut self, rng: &mut dyn RngCore) -> BlockType {
+        let blocktype = if !self.started {
+            self.started = true;
+            let first = [
+                BlockType::Line,
+                BlockType::L,
+                BlockType::ReverseL,
+                BlockType::T,
+            ];
+            first[rng.gen_range(0, first.len())]
+        } else {
+            let mut blocktype = TYPES[rng.gen_range(0, TYPES.len())];
+            for _ in 1..self.rolls {
+                if !self.history.contains(&blocktype) {
+                    break;
+                }
+                blocktype = TYPES[rng.gen_range(0, TYPES.len())];
+            }
+            blocktype
+        };
+
+        self.history.pop_front();
+        self.history.push_back(blocktype);
+        blocktype
+    }
+
+    fn box_clone(&self) -> Box<dyn Randomizer> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn deal(kind: RandomizerKind, seed: u64, count: usize) -> Vec<BlockType> {
+        let mut randomizer = kind.build();
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..count).map(|_| randomizer.next(&mut rng)).collect()
+    }
+
+    fn count(blocks: &[BlockType], blocktype: BlockType) -> usize {
+        blocks.iter().filter(|&&block| block == blocktype).count()
+    }
+
+    #[test]
+    fn seven_bag_deals_every_block_once_per_bag() {
+        for seed in 0..20 {
+            for bag in deal(RandomizerKind::SevenBag, seed, 70).chunks(7) {
+                for &blocktype in TYPES.iter() {
+                    assert_eq!(count(bag, blocktype), 1, "seed {}", seed);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fourteen_bag_deals_every_block_twice_per_bag() {
+        for seed in 0..20 {
+            for bag in deal(RandomizerKind::FourteenBag, seed, 70).chunks(14) {
+                for &blocktype in TYPES.iter() {
+                    assert_eq!(count(bag, blocktype), 2, "seed {}", seed);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn nes_repeats_less_than_pure_random() {
+        let repeats =
+            |blocks: Vec<BlockType>| blocks.windows(2).filter(|pair| pair[0] == pair[1]).count();
+        let nes = repeats(deal(RandomizerKind::Nes, 1, 7000));
+        let random = repeats(deal(RandomizerKind::Random, 1, 7000));
+        assert!(nes < random, "{} repeats against {}", nes, random);
+    }
+
+    #[test]
+    fn tgm_never_starts_with_an_overhang() {
+        for seed in 0..100 {
+            let first = deal(RandomizerKind::Tgm, seed, 1)[0];
+            assert!(
+                ![BlockType::S, BlockType::Z, BlockType::Square].contains(&first),
+                "seed {} started with {:?}",
+                seed,
+                first
+            );
+        }
+    }
+
+    #[test]
+    fn parses_every_randomizer() {
+        assert_eq!(RandomizerKind::parse("7bag"), Ok(RandomizerKind::SevenBag));
+        assert_eq!(
+            RandomizerKind::parse("14bag"),
+            Ok(RandomizerKind::FourteenBag)
+        );
+        assert_eq!(RandomizerKind::parse("random"), Ok(RandomizerKind::Random));
+        assert_eq!(RandomizerKind::parse("nes"), Ok(RandomizerKind::Nes));
+        assert_eq!(RandomizerKind::parse("tgm"), Ok(RandomizerKind::Tgm));
+        assert!(RandomizerKind::parse("8bag").is_err());
+    }
+
+    #[test]
+    fn same_seed_deals_the_same_blocks() {
+        let kinds = [
+            RandomizerKind::SevenBag,
+            RandomizerKind::FourteenBag,
+            RandomizerKind::Random,
+            RandomizerKind::Nes,
+            RandomizerKind::Tgm,
+        ];
+        for &kind in kinds.iter() {
+            assert_eq!(deal(kind, 7, 50), deal(kind, 7, 50), "{:?}", kind);
+        }
+        assert_ne!(
+            deal(RandomizerKind::SevenBag, 7, 50),
+            deal(RandomizerKind::SevenBag, 8, 50)
+        );
+    }
+}
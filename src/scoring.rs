@@ -0,0 +1,264 @@
+/// Points for clearing 0, 1, 2, 3, or 4 lines at once, before
+/// they're multiplied by the level
+const LINE_CLEAR_POINTS: [usize; 5] = [0, 100, 300, 500, 800];
+const TSPIN_POINTS: [usize; 4] = [400, 800, 1200, 1600];
+const TSPIN_MINI_POINTS: [usize; 3] = [100, 200, 400];
+/// Bonus for emptying the board with 0, 1, 2, 3, or 4 lines
+const PERFECT_CLEAR_POINTS: [usize; 5] = [0, 800, 1200, 1800, 2000];
+const BACK_TO_BACK_PERFECT_CLEAR_POINTS: usize = 3200;
+
+const COMBO_POINTS: usize = 50;
+const SOFT_DROP_POINTS: usize = 1;
+const HARD_DROP_POINTS: usize = 2;
+
+/// Lines of garbage sent for clearing 0, 1, 2, 3, or 4 lines at once
+const LINE_CLEAR_ATTACK: [usize; 5] = [0, 0, 1, 2, 4];
+const TSPIN_ATTACK: [usize; 4] = [0, 2, 4, 6];
+const TSPIN_MINI_ATTACK: [usize; 3] = [0, 0, 1];
+/// Extra lines sent at each combo count, the last one
+/// being used for any longer combo
+const COMBO_ATTACK: [usize; 11] = [0, 1, 1, 2, 2, 3, 3, 4, 4, 4, 5];
+const BACK_TO_BACK_ATTACK: usize = 1;
+const PERFECT_CLEAR_ATTACK: usize = 10;
+
+/// A T block that was rotated into place, as
+/// judged by the 3-corner rule when it locks
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TSpin {
+    Mini,
+    Full,
+}
+
+/// Everything about a single placement that affects its score
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Clear {
+    pub lines: usize,
+    pub tspin: Option<TSpin>,
+    /// whether the board was left empty
+    pub perfect: bool,
+}
+
+/// Guideline scoring, fed by the engine every time
+/// a block is dropped or placed
+#[derive(Clone, Debug, Default)]
+pub struct Score {
+    pub points: usize,
+    /// placements in a row that cleared lines, minus one,
+    /// or None if the last placement didn't clear anything
+    pub combo: Option<usize>,
+    /// whether the last line clear was difficult, so that
+    /// the next difficult clear gets a bonus
+    pub back_to_back: bool,
+}
+
+impl Score {
+    /// awards points for rows moved by soft dropping
+    pub fn soft_drop(&mut self, rows: usize) {
+        self.points += rows * SOFT_DROP_POINTS;
+    }
+
+    /// awards points for rows moved by hard dropping
+    pub fn hard_drop(&mut self, rows: usize) {
+        self.points += rows * HARD_DROP_POINTS;
+    }
+
+    /// scores a placement at `level`, updating the combo
+    /// and back-to-back state, and returns the points awarded
+    pub fn clear(&mut self, clear: Clear, level: usize) -> usize {
+        let lines = clear.lines;
+        let base = match clear.tspin {
+            Some(TSpin::Full) => TSPIN_POINTS[lines.min(3)],
+            Some(TSpin::Mini) => TSPIN_MINI_POINTS[lines.min(2)],
+            None => LINE_CLEAR_POINTS[lines.min(4)],
+        };
+        let mut points = base * level;
+
+        if lines == 0 {
+            self.combo = None;
+            self.points += points;
+            return points;
+        }
+
+        let difficult = lines >= 4 || clear.tspin.is_some();
+        let back_to_back = difficult && self.back_to_back;
+        if back_to_back {
+            points = points * 3 / 2;
+        }
+        self.back_to_back = difficult;
+
+        if clear.perfect {
+            points += if back_to_back && lines >= 4 {
+                BACK_TO_BACK_PERFECT_CLEAR_POINTS
+            } else {
+                PERFECT_CLEAR_POINTS[lines.min(4)]
+            } * level;
+        }
+
+        let combo = self.combo.map_or(0, |combo| combo + 1);
+        points += COMBO_POINTS * combo * level;
+        self.combo = Some(combo);
+
+        self.points += points;
+        points
+    }
+}
+
+/// lines of garbage a placement would send to an opponent in versus
+/// play, following the guideline. `back_to_back` is whether the last
+/// line clear was difficult, and `combo` is the combo after placing
+pub fn attack(clear: Clear, back_to_back: bool, combo: Option<usize>) -> usize {
+    let lines = clear.lines;
+    if lines == 0 {
+        return 0;
+    }
+
+    let mut attack = match clear.tspin {
+        Some(TSpin::Full) => TSPIN_ATTACK[lines.min(3)],
+        Some(TSpin::Mini) => TSPIN_MINI_ATTACK[lines.min(2)],
+        None => LINE_CLEAR_ATTACK[lines.min(4)],
+    };
+    if back_to_back && (lines >= 4 || clear.tspin.is_some()) {
+        attack += BACK_TO_BACK_ATTACK;
+    }
+    if let Some(combo) = combo {
+        attack += COMBO_ATTACK[combo.min(COMBO_ATTACK.len() - 1)];
+    }
+    if clear.perfect {
+        attack += PERFECT_CLEAR_ATTACK;
+    }
+    attack
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(lines: usize) -> Clear {
+        Clear {
+            lines,
+            ..Clear::default()
+        }
+    }
+
+    #[test]
+    fn line_clears_scale_with_level() {
+        let mut score = Score::default();
+        assert_eq!(score.clear(lines(1), 1), 100);
+        assert_eq!(score.clear(lines(0), 1), 0);
+        assert_eq!(score.clear(lines(4), 3), 2400);
+        assert_eq!(score.points, 2500);
+    }
+
+    #[test]
+    fn back_to_back_survives_placements_without_clears() {
+        let mut score = Score::default();
+        assert_eq!(score.clear(lines(4), 1), 800);
+        assert_eq!(score.clear(lines(0), 1), 0);
+        assert_eq!(score.clear(lines(4), 1), 1200);
+    }
+
+    #[test]
+    fn easy_clears_break_back_to_back() {
+        let mut score = Score::default();
+        score.clear(lines(4), 1);
+        score.clear(lines(0), 1);
+        score.clear(lines(1), 1);
+        score.clear(lines(0), 1);
+        assert_eq!(score.clear(lines(4), 1), 800);
+    }
+
+    #[test]
+    fn combos_add_up_until_a_placement_clears_nothing() {
+        let mut score = Score::default();
+        assert_eq!(score.clear(lines(1), 2), 200);
+        assert_eq!(score.clear(lines(1), 2), 300);
+        assert_eq!(score.clear(lines(1), 2), 400);
+        assert_eq!(score.combo, Some(2));
+
+        score.clear(lines(0), 2);
+        assert_eq!(score.combo, None);
+        assert_eq!(score.clear(lines(1), 2), 200);
+    }
+
+    #[test]
+    fn drops_score_per_row() {
+        let mut score = Score::default();
+        score.soft_drop(3);
+        score.hard_drop(10);
+        assert_eq!(score.points, 23);
+    }
+
+    #[test]
+    fn tspins_score_their_own_table() {
+        let mut score = Score::default();
+        let double = Clear {
+            lines: 2,
+            tspin: Some(TSpin::Full),
+            perfect: false,
+        };
+        assert_eq!(score.clear(double, 1), 1200);
+
+        let mini = Clear {
+            lines: 0,
+            tspin: Some(TSpin::Mini),
+            perfect: false,
+        };
+        assert_eq!(score.clear(mini, 2), 200);
+        assert_eq!(score.combo, None);
+        assert!(score.back_to_back);
+    }
+
+    #[test]
+    fn perfect_clears_add_a_bonus() {
+        let mut score = Score::default();
+        let single = Clear {
+            lines: 1,
+            tspin: None,
+            perfect: true,
+        };
+        assert_eq!(score.clear(single, 1), 900);
+
+        let mut score = Score {
+            back_to_back: true,
+            ..Score::default()
+        };
+        let tetris = Clear {
+            lines: 4,
+            tspin: None,
+            perfect: true,
+        };
+        assert_eq!(score.clear(tetris, 1), 1200 + 3200);
+    }
+
+    #[test]
+    fn attack_follows_the_guideline() {
+        let tspin = |lines, kind| Clear {
+            lines,
+            tspin: Some(kind),
+            perfect: false,
+        };
+        let attacks: Vec<usize> = (0..=4).map(|n| attack(lines(n), false, None)).collect();
+        assert_eq!(attacks, vec![0, 0, 1, 2, 4]);
+        assert_eq!(attack(tspin(1, TSpin::Full), false, None), 2);
+        assert_eq!(attack(tspin(2, TSpin::Full), false, None), 4);
+        assert_eq!(attack(tspin(3, TSpin::Full), false, None), 6);
+        assert_eq!(attack(tspin(2, TSpin::Mini), false, None), 1);
+        assert_eq!(attack(tspin(0, TSpin::Full), true, Some(3)), 0);
+    }
+
+    #[test]
+    fn attack_bonuses_add_up() {
+        assert_eq!(attack(lines(4), true, None), 5);
+        assert_eq!(attack(lines(2), true, None), 1);
+        assert_eq!(attack(lines(1), false, Some(0)), 0);
+        assert_eq!(attack(lines(1), false, Some(1)), 1);
+        assert_eq!(attack(lines(1), false, Some(50)), 5);
+
+        let perfect = Clear {
+            lines: 4,
+            tspin: None,
+            perfect: true,
+        };
+        assert_eq!(attack(perfect, true, Some(2)), 4 + 1 + 1 + 10);
+    }
+}